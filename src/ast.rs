@@ -1,23 +1,31 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::bigint::BigInt;
+use crate::ordered_map::OrderedMap;
 
 #[derive(Debug, Clone)]
 pub enum Expr {
     Identifier(String),
     String(String),
+    /// `"Hello {name}"`: literal pieces are `Expr::String`, placeholders are
+    /// arbitrary expressions.
+    Interpolated(Vec<Expr>),
     Boolean(bool),
-    Number(f64),
+    Integer(i64),
+    Float(f64),
     List(Vec<Expr>),
     Assignment {
         name: String,
         value: Box<Expr>,
-        type_hint: Option<String>, // :: Support.
+        type_hint: Option<TypeExpr>, // :: Support.
         mutable: bool,
     },
     FunctionDef{
         name: String,
-        params: Vec<(String,String)>, //name, type
+        params: Vec<(String,TypeExpr)>, //name, type
         body: Vec<Expr>,
-        return_type: String,
+        return_type: TypeExpr,
     },
     // End FunctionDef block.
      // --------------------
@@ -25,35 +33,143 @@ pub enum Expr {
     FunctionCall{
         name: String,
         args: Vec<Expr>,
+        line: usize, // call site, for stack traces
     },
     Comment,
     Empty,
 
-MapLiteral(HashMap<String,Expr>),
-MapAccess{
-    map: Box<Expr>,
-    key: Box<Expr>,
-},
+    MapLiteral(Vec<(Expr, Expr)>), // key, value
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Slice {
+        target: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    },
+    Reassign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Binary {
+        op: String,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Unary {
+        op: String,
+        operand: Box<Expr>,
+    },
+    Lambda {
+        params: Vec<(String, TypeExpr)>,
+        return_type: TypeExpr,
+        body: Vec<Expr>,
+    },
+    /// A call on any expression that evaluates to a function value.
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+        line: usize,
+    },
+    Try {
+        body: Vec<Expr>,
+        error_var: Option<String>,
+        handler: Vec<Expr>,
+    },
     
     Import{
         module: String,
         alias: Option<String>,
     },
     Return(Box<Expr>),
+    Break,
+    Continue,
+    If {
+        branches: Vec<(Expr, Vec<Expr>)>, // condition, body
+        else_branch: Option<Vec<Expr>>,
+    },
+    While {
+        condition: Box<Expr>,
+        body: Vec<Expr>,
+    },
+    For {
+        var: String,
+        iterable: Box<Expr>,
+        body: Vec<Expr>,
+    },
     ModuleDef{
         name:String,
         body:Vec<Expr>,
     },
     ModuleImport(String),
+    TypeAlias {
+        name: String,
+        target: TypeExpr,
+    },
 
 }
-#[derive(Debug, Clone)]
+/// A type as written in source: `Text`, `List<int>`, `Map<Text, List<f64>>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeExpr {
+    pub name: String,
+    pub args: Vec<TypeExpr>,
+}
+
+impl TypeExpr {
+    pub fn simple(name: &str) -> Self {
+        TypeExpr { name: name.to_string(), args: Vec::new() }
+    }
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.args.is_empty() {
+            let args = self.args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+            write!(f, "<{}>", args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
-    Integer(i32),
+    Integer(i64),
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Boolean(bool),
     Void,
-    Map(HashMap<String, Value>),
+    Map(OrderedMap<Value>),
     List(Vec<Value>),
+    Function(FunctionRef),
+}
+
+/// A function used as a value.
+#[derive(Debug, Clone)]
+pub enum FunctionRef {
+    /// A function registered by name (native or declared with `fn name`).
+    Named(String),
+    Closure(Rc<Closure>),
+}
+
+/// An anonymous function together with the bindings it captured, by value,
+/// when it was created.
+#[derive(Debug)]
+pub struct Closure {
+    pub params: Vec<(String, TypeExpr)>,
+    pub return_type: TypeExpr,
+    pub body: Vec<Expr>,
+    pub captured: HashMap<String, Value>,
+}
+
+impl PartialEq for FunctionRef {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FunctionRef::Named(a), FunctionRef::Named(b)) => a == b,
+            (FunctionRef::Closure(a), FunctionRef::Closure(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,401 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Each limb holds nine decimal digits, which keeps printing and parsing
+/// simple and lets limb products fit in a `u64`.
+const BASE: u64 = 1_000_000_000;
+
+/// An arbitrary-precision signed integer, backing Lunaria's `BigInt` type.
+/// Limbs are stored least significant first with no trailing zeros, and zero
+/// is never negative, so the derived equality is numeric equality.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn from_i64(n: i64) -> Self {
+        let mut magnitude = n.unsigned_abs();
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE) as u32);
+            magnitude /= BASE;
+        }
+        BigInt { negative: n < 0, limbs }
+    }
+
+    /// Parses an optionally signed string of decimal digits.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let bytes = digits.as_bytes();
+        let mut limbs = Vec::new();
+        let mut end = bytes.len();
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            let chunk = std::str::from_utf8(&bytes[start..end]).ok()?;
+            limbs.push(chunk.parse().ok()?);
+            end = start;
+        }
+        Some(Self::normalized(negative, limbs))
+    }
+
+    /// Returns the value as an `i64` if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut magnitude: i128 = 0;
+        for &limb in self.limbs.iter().rev() {
+            magnitude = magnitude * BASE as i128 + limb as i128;
+            if magnitude > i64::MAX as i128 + 1 {
+                return None;
+            }
+        }
+        let value = if self.negative { -magnitude } else { magnitude };
+        i64::try_from(value).ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.limbs.iter().rev().fold(0.0, |acc, &limb| acc * BASE as f64 + limb as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    /// Base-10 logarithm of the magnitude from its two leading limbs, close
+    /// enough to size a result before computing it (negative infinity for
+    /// zero).
+    pub fn log10(&self) -> f64 {
+        let leading = self.limbs.iter().rev().take(2).fold(0.0, |acc, &limb| acc * BASE as f64 + limb as f64);
+        leading.log10() + (self.limbs.len().saturating_sub(2) * 9) as f64
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Truncating division and remainder, matching the semantics of `Int`.
+    /// Returns `None` when dividing by zero.
+    pub fn div_rem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.limbs, &divisor.limbs);
+        Some((
+            Self::normalized(self.negative != divisor.negative, quotient),
+            Self::normalized(self.negative, remainder),
+        ))
+    }
+
+    fn normalized(negative: bool, limbs: Vec<u32>) -> Self {
+        let limbs = trim(limbs);
+        let negative = negative && !limbs.is_empty();
+        BigInt { negative, limbs }
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push((sum % BASE) as u32);
+        carry = sum / BASE;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// `a - b`, where `a` must not be smaller than `b`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut diff = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += BASE as i64;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u64; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let cur = result[i + j] + x as u64 * y as u64 + carry;
+            result[i + j] = cur % BASE;
+            carry = cur / BASE;
+        }
+        result[i + b.len()] += carry;
+    }
+    result.into_iter().map(|limb| limb as u32).collect()
+}
+
+/// Schoolbook long division. Each quotient limb is found by binary search,
+/// which is slow for huge divisors but simple and exact.
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for i in (0..a.len()).rev() {
+        remainder.insert(0, a[i]);
+        remainder = trim(remainder);
+        let (mut low, mut high) = (0u64, BASE - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            let product = trim(mul_magnitude(b, &[mid as u32]));
+            if cmp_magnitude(&product, &remainder) == Ordering::Greater {
+                high = mid - 1;
+            } else {
+                low = mid;
+            }
+        }
+        if low > 0 {
+            let product = trim(mul_magnitude(b, &[low as u32]));
+            remainder = trim(sub_magnitude(&remainder, &product));
+        }
+        quotient[i] = low as u32;
+    }
+    (quotient, remainder)
+}
+
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::normalized(self.negative, add_magnitude(&self.limbs, &other.limbs));
+        }
+        match cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::normalized(other.negative, sub_magnitude(&other.limbs, &self.limbs)),
+            _ => BigInt::normalized(self.negative, sub_magnitude(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::normalized(self.negative != other.negative, mul_magnitude(&self.limbs, &other.limbs))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::normalized(!self.negative, self.limbs.clone())
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((most, rest)) = self.limbs.split_last() else {
+            return write!(f, "0");
+        };
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", most)?;
+        for limb in rest.iter().rev() {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse(text).unwrap()
+    }
+
+    fn from_i128(n: i128) -> BigInt {
+        big(&n.to_string())
+    }
+
+    const SAMPLES: [i64; 12] = [
+        0,
+        1,
+        -1,
+        7,
+        -7,
+        999_999_999,
+        1_000_000_000,
+        -1_000_000_000,
+        123_456_789_012,
+        -987_654_321_987_654_321,
+        i64::MAX,
+        i64::MIN,
+    ];
+
+    #[test]
+    fn mixed_sign_arithmetic_matches_i128() {
+        for &a in &SAMPLES {
+            for &b in &SAMPLES {
+                let (x, y) = (BigInt::from_i64(a), BigInt::from_i64(b));
+                let (a, b) = (a as i128, b as i128);
+                assert_eq!(&x + &y, from_i128(a + b), "{} + {}", a, b);
+                assert_eq!(&x - &y, from_i128(a - b), "{} - {}", a, b);
+                assert_eq!(&x * &y, from_i128(a * b), "{} * {}", a, b);
+                if b != 0 {
+                    let (q, r) = x.div_rem(&y).unwrap();
+                    assert_eq!((q, r), (from_i128(a / b), from_i128(a % b)), "{} / {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn carries_and_borrows_cross_limb_boundaries() {
+        let one = BigInt::from_i64(1);
+        assert_eq!(&big("999999999999999999") + &one, big("1000000000000000000"));
+        assert_eq!(&big("1000000000000000000000000000") - &one, big("999999999999999999999999999"));
+        assert_eq!(&big("-1000000000000000000") + &one, big("-999999999999999999"));
+        assert_eq!(&one - &big("1000000000000000000"), big("-999999999999999999"));
+        assert_eq!(&big("1000000000000000000") - &big("999999999"), big("999999999000000001"));
+        assert_eq!(
+            &big("999999999999999999") * &big("999999999999999999"),
+            big("999999999999999998000000000000000001")
+        );
+        assert_eq!(&big("-1000000000") * &big("1000000000"), big("-1000000000000000000"));
+    }
+
+    #[test]
+    fn log10_sizes_the_magnitude() {
+        assert_eq!(BigInt::from_i64(1).log10(), 0.0);
+        assert_eq!(BigInt::from_i64(-1000).log10(), 3.0);
+        assert_eq!(big("1000000000000000000").log10(), 18.0);
+        assert!((big(&"9".repeat(40)).log10() - 40.0).abs() < 1e-9);
+        assert!((big(&format!("2{}", "0".repeat(50))).log10() - 50.301_03).abs() < 1e-5);
+        assert_eq!(BigInt::zero().log10(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn zero_results_are_never_negative() {
+        let x = big("-123456789123456789");
+        assert_eq!(&x - &x, BigInt::zero());
+        assert_eq!(&x + &-&x, BigInt::zero());
+        assert_eq!(&x * &BigInt::zero(), BigInt::zero());
+        assert_eq!(-&BigInt::zero(), BigInt::zero());
+        assert_eq!((&x - &x).to_string(), "0");
+        let (_, r) = x.div_rem(&big("3")).unwrap();
+        assert_eq!(r, BigInt::zero());
+    }
+
+    #[test]
+    fn division_of_large_values_truncates_toward_zero() {
+        let cases = [
+            ("123456789012345678901234567890123456789", "-987654321987654321"),
+            ("-123456789012345678901234567890123456789", "1000000000"),
+            ("1000000000000000000000000000000000000", "999999999999999999"),
+            ("-5", "1000000000000000000000"),
+        ];
+        for (a, b) in cases {
+            let (a, b) = (big(a), big(b));
+            let (q, r) = a.div_rem(&b).unwrap();
+            assert_eq!(&(&q * &b) + &r, a, "{} / {}", a, b);
+            assert!(cmp_magnitude(&r.limbs, &b.limbs) == Ordering::Less, "{} % {}", a, b);
+            assert!(r.is_zero() || r.negative == a.negative, "{} % {}", a, b);
+        }
+        let (q, r) = big("1000000000000000000000000000000000000").div_rem(&big("1000000000000000000")).unwrap();
+        assert_eq!((q, r), (big("1000000000000000000"), BigInt::zero()));
+    }
+
+    #[test]
+    fn division_by_zero_is_none() {
+        assert!(big("42").div_rem(&BigInt::zero()).is_none());
+        assert!(BigInt::zero().div_rem(&BigInt::zero()).is_none());
+        assert!(big("-1000000000000000000000").div_rem(&big("-0")).is_none());
+    }
+
+    #[test]
+    fn parses_signed_decimal_digits_only() {
+        assert_eq!(big("+42"), BigInt::from_i64(42));
+        assert_eq!(big("-42"), BigInt::from_i64(-42));
+        assert_eq!(big("-0"), BigInt::zero());
+        assert_eq!(big("000000000000000000123"), BigInt::from_i64(123));
+        for text in ["", "-", "+", "--1", "+-1", "12a", " 1", "1 ", "1.5", "1_000", "٣"] {
+            assert!(BigInt::parse(text).is_none(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn displays_inner_limbs_with_leading_zeros() {
+        for text in ["0", "7", "-7", "1000000000", "-1000000001", "1000000000000000000", "123000000004000000005"] {
+            assert_eq!(big(text).to_string(), text);
+        }
+        assert_eq!(big("-000").to_string(), "0");
+        assert_eq!(big("+0001000000000").to_string(), "1000000000");
+    }
+
+    #[test]
+    fn converts_to_i64_only_when_it_fits() {
+        assert_eq!(big("9223372036854775807").to_i64(), Some(i64::MAX));
+        assert_eq!(big("-9223372036854775808").to_i64(), Some(i64::MIN));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+        assert_eq!(big("100000000000000000000000000000").to_i64(), None);
+        assert_eq!(big("-1500000000").to_f64(), -1.5e9);
+    }
+
+    #[test]
+    fn orders_by_sign_then_magnitude() {
+        let sorted = ["-1000000000000", "-999999999", "-1", "0", "1", "999999999", "1000000000000"];
+        for pair in sorted.windows(2) {
+            assert!(big(pair[0]) < big(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+}
@@ -5,7 +5,7 @@ pub fn load_builtin_modules(grim: &mut Grimoire) {
     let mut console = Module::new("console");
 
     console.insert("out", Function::Native(|args| {
-        if let Some(Value::String(s)) = args.get(0) {
+        if let Some(Value::String(s)) = args.first() {
             println!("{}", s);
         }
         Value::Void
@@ -0,0 +1,74 @@
+//! The clock behind the `time` module. Scripts normally see the system
+//! clock; embedders and tests can install `Clock::fake` instead, which
+//! only moves when the script sleeps or the host calls `advance`, so the
+//! output of a program that prints times is reproducible.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone)]
+pub enum Clock {
+    System {
+        /// Origin of `monotonic_ms`.
+        start: Instant,
+    },
+    Fake {
+        /// Milliseconds since the Unix epoch.
+        now_ms: i64,
+        /// Milliseconds elapsed since the clock was created.
+        elapsed_ms: i64,
+    },
+}
+
+impl Clock {
+    pub fn system() -> Self {
+        Clock::System { start: Instant::now() }
+    }
+
+    /// A clock frozen at `now_ms` milliseconds since the Unix epoch.
+    pub fn fake(now_ms: i64) -> Self {
+        Clock::Fake { now_ms, elapsed_ms: 0 }
+    }
+
+    /// Wall-clock time in milliseconds since the Unix epoch.
+    pub fn now_ms(&self) -> i64 {
+        match self {
+            Clock::System { .. } => match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(since) => since.as_millis() as i64,
+                // Un reloj del sistema anterior a 1970.
+                Err(before) => -(before.duration().as_millis() as i64),
+            },
+            Clock::Fake { now_ms, .. } => *now_ms,
+        }
+    }
+
+    /// Milliseconds since the clock was created; never goes backwards.
+    pub fn monotonic_ms(&self) -> i64 {
+        match self {
+            Clock::System { start } => start.elapsed().as_millis() as i64,
+            Clock::Fake { elapsed_ms, .. } => *elapsed_ms,
+        }
+    }
+
+    /// Blocks for `ms` milliseconds, or just advances a fake clock.
+    pub fn sleep(&mut self, ms: u64) {
+        match self {
+            Clock::System { .. } => std::thread::sleep(Duration::from_millis(ms)),
+            Clock::Fake { .. } => self.advance(ms),
+        }
+    }
+
+    /// Moves a fake clock forward; the system clock cannot be moved.
+    pub fn advance(&mut self, ms: u64) {
+        if let Clock::Fake { now_ms, elapsed_ms } = self {
+            let ms = i64::try_from(ms).unwrap_or(i64::MAX);
+            *now_ms = now_ms.saturating_add(ms);
+            *elapsed_ms = elapsed_ms.saturating_add(ms);
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::system()
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Type,
+    Name,
+    Arity,
+    Index,
+    Key,
+    Mutability,
+    Arithmetic,
+    Overflow,
+    Value,
+    Control,
+    StackOverflow,
+    Io,
+    Permission,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Type => "TypeError",
+            ErrorKind::Name => "NameError",
+            ErrorKind::Arity => "ArityError",
+            ErrorKind::Index => "IndexError",
+            ErrorKind::Key => "KeyError",
+            ErrorKind::Mutability => "MutabilityError",
+            ErrorKind::Arithmetic => "ArithmeticError",
+            ErrorKind::Overflow => "OverflowError",
+            ErrorKind::Value => "ValueError",
+            ErrorKind::Control => "ControlFlowError",
+            ErrorKind::StackOverflow => "StackOverflowError",
+            ErrorKind::Io => "IOError",
+            ErrorKind::Permission => "PermissionError",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One entry of a Lunaria stack trace: the function that was running and
+/// the line it was called from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    pub line: usize,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "en '{}' (llamada en la línea {})", self.function, self.line)
+        } else {
+            write!(f, "en '{}'", self.function)
+        }
+    }
+}
+
+/// An error raised while evaluating a Lunaria program.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Innermost call first.
+    pub trace: Vec<Frame>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RuntimeError { kind, message: message.into(), trace: Vec::new() }
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Type, message)
+    }
+
+    /// Records that the error unwound through a call to `function` made at `line`.
+    pub fn with_frame(mut self, function: &str, line: usize) -> Self {
+        self.trace.push(Frame { function: function.to_string(), line });
+        self
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)?;
+        for frame in &self.trace {
+            write!(f, "\n    {}", frame)?;
+        }
+        Ok(())
+    }
+}
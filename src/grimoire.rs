@@ -63,7 +63,7 @@ impl Grimoire {
     }
     // Nueva función
     pub fn call_global_function(&self, function: &str, args: Vec<Value>) -> Option<Value>{
-        for (_name, module) in &self.modules{
+        for module in self.modules.values(){
             if let Some(func) = module.get(function){
                 return match func{
                     Function::Native(f) => Some(f(args)),
@@ -1,287 +1,1290 @@
 
 use std::collections::{HashSet, HashMap};
-use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use crate::ast::Expr;
-use crate::ast::Value;
+use std::rc::Rc;
+use crate::ast::{Closure, FunctionRef, TypeExpr, Value};
+use crate::bigint::BigInt;
+use crate::clock::Clock;
+use crate::ordered_map::OrderedMap;
+use crate::permissions::Permissions;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::types::{Type, TypeRegistry};
+use crate::natives;
 
 #[derive(Clone)]
 pub enum Function {
-    Native(fn(Vec<Value>) -> Value),
+    Native(fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError>),
     UserDefined {
         name: String,
-        params: Vec<(String, String)>,
+        params: Vec<(String, TypeExpr)>,
         body: Vec<Expr>,
-        return_type: String,
+        return_type: TypeExpr,
     },
 }
 
+/// How evaluation leaves a statement early: `return`, `break`, `continue`,
+/// or a runtime error. Only errors may escape a function call.
+#[derive(Debug)]
+pub enum Control {
+    Return(Value),
+    Break,
+    Continue,
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Control {
+    fn from(e: RuntimeError) -> Self {
+        Control::Error(e)
+    }
+}
+
+impl Control {
+    /// Turns a signal that reached a function or program boundary into an error.
+    fn into_error(self) -> RuntimeError {
+        match self {
+            Control::Error(e) => e,
+            Control::Return(_) => RuntimeError::new(ErrorKind::Control, "'return' fuera de una función"),
+            Control::Break => RuntimeError::new(ErrorKind::Control, "'break' fuera de un bucle"),
+            Control::Continue => RuntimeError::new(ErrorKind::Control, "'continue' fuera de un bucle"),
+        }
+    }
+}
+
 pub struct Interpreter {
     pub env: HashMap<String, Value>,
     pub functions: HashMap<String, Function>,
     pub imported_modules: HashSet<String>,
+    pub types: TypeRegistry,
+    /// Bindings declared with `define mut`, with the type they must keep.
+    pub mutables: HashMap<String, Type>,
+    /// Number of user-defined calls currently running.
+    pub call_depth: usize,
+    /// Calls nested deeper than this raise a `StackOverflowError`.
+    pub max_call_depth: usize,
+    /// Reports declarations, assignments and the start of `main` on stderr.
+    pub verbose: bool,
+    /// Bytes of native stack evaluation may use below the point where it
+    /// started. Expressions nest inside calls, so the call depth alone does
+    /// not bound the stack; past this a `StackOverflowError` is raised
+    /// instead of overflowing the thread's stack.
+    pub stack_limit: usize,
+    /// Stack position where evaluation started, see `stack_limit`.
+    stack_base: Option<usize>,
+    /// Command-line arguments passed to `fn main(args::List<Text>)`.
+    pub script_args: Vec<String>,
+    /// Read-only values provided by native modules, such as `math.pi`.
+    /// Unlike `env`, they are visible from every function.
+    pub constants: HashMap<String, Value>,
+    /// Source of the `random` module; seeded from the OS unless
+    /// `seed_random` (or `random.seed`) fixes it.
+    pub rng: StdRng,
+    /// What the script may access outside the interpreter; nothing
+    /// unless granted.
+    pub permissions: Permissions,
+    /// Source of the `time` module; the system clock unless replaced by
+    /// `Clock::fake`.
+    pub clock: Clock,
 }
 
+/// Default limit on nested user-defined calls.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// Default `stack_limit`, which leaves a margin inside the 2 MiB stack Rust
+/// gives spawned threads.
+pub const DEFAULT_STACK_LIMIT: usize = 1024 * 1024;
+
 impl Interpreter {
     pub fn new() -> Self {
         let mut interpreter = Self {
             env: HashMap::new(),
             functions: HashMap::new(),
             imported_modules: HashSet::new(),
+            types: TypeRegistry::new(),
+            mutables: HashMap::new(),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            verbose: false,
+            stack_limit: DEFAULT_STACK_LIMIT,
+            stack_base: None,
+            script_args: Vec::new(),
+            constants: HashMap::new(),
+            rng: StdRng::from_entropy(),
+            permissions: Permissions::default(),
+            clock: Clock::system(),
         };
-        register_native_functions(&mut interpreter);
+        natives::register(&mut interpreter);
         interpreter
     }
 
-    pub fn interpret(&mut self, expressions: Vec<Expr>) {
+    /// Registers a native function under `name` (e.g. `"math.sqrt"`).
+    pub fn define_native(&mut self, name: &str, f: fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError>) {
+        self.functions.insert(name.to_string(), Function::Native(f));
+    }
+
+    /// Registers a read-only value under `name` (e.g. `"math.pi"`).
+    pub fn define_constant(&mut self, name: &str, value: Value) {
+        self.constants.insert(name.to_string(), value);
+    }
+
+    /// Makes the `random` module reproducible.
+    pub fn seed_random(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Runs a program and returns its process exit code: the value returned
+    /// by `main`, or 1 if a runtime error went uncaught.
+    pub fn interpret(&mut self, expressions: Vec<Expr>) -> i32 {
+        match self.run(expressions) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("❌ Error de ejecución: {}", e);
+                1
+            }
+        }
+    }
+
+    /// Runs a program, returning its exit code or the first uncaught
+    /// runtime error.
+    pub fn run(&mut self, expressions: Vec<Expr>) -> Result<i32, RuntimeError> {
+        self.stack_base = Some(stack_position());
+        // Los alias de tipo se registran antes que las funciones que los usan.
+        for expr in &expressions {
+            if matches!(expr, Expr::TypeAlias { .. }) {
+                self.eval(expr.clone()).map_err(Control::into_error)?;
+            }
+        }
         for expr in &expressions {
             if matches!(expr, Expr::FunctionDef {..} | Expr::ModuleDef {..}) {
-                self.eval(expr.clone());
+                self.eval(expr.clone()).map_err(Control::into_error)?;
             }
         }
         for expr in expressions {
-            if !matches!(expr, Expr::FunctionDef { .. } | Expr::ModuleDef { .. }) {
-                let _ = self.eval(expr);
+            if !matches!(expr, Expr::FunctionDef { .. } | Expr::ModuleDef { .. } | Expr::TypeAlias { .. }) {
+                self.eval(expr).map_err(Control::into_error)?;
             }
         }
-        self.entry_point();
+        self.entry_point()
     }
 
-    pub fn eval(&mut self, expr: Expr) -> Value {
+    pub fn eval(&mut self, expr: Expr) -> Result<Value, Control> {
+        self.check_stack()?;
         match expr {
             Expr::Assignment { name, value, type_hint, mutable } => {
-                let val = self.eval_expr(*value);
+                let mut val = self.eval_expr(*value)?;
+                let mut declared = Type::Any;
                 if let Some(expected) = &type_hint {
-                    let matches = match (expected.as_str(), &val) {
-                        ("string", Value::String(_)) => true,
-                        ("int", Value::Integer(_)) => true,
-                        ("bool", Value::Boolean(_)) => true,
-                        ("void", Value::Void) => true,
-                        _ => false,
-                    };
-                    if !matches {
-                        println!("❌ Error de tipo: Se esperaba '{}' pero se recibió '{:?}'", expected, val);
+                    let ty = self.resolve_type(expected)?;
+                    if !ty.accepts(&val) {
+                        return Err(RuntimeError::type_error(format!(
+                            "'{}' se declaró como '{}' pero recibió '{}' ({:?})",
+                            name, ty, Type::of(&val), val
+                        )).into());
                     }
+                    val = ty.coerce(val);
+                    declared = ty;
                 }
                 if mutable {
-                    println!("📦 Asignado mutable: {} := {:?}", name, val);
+                    self.mutables.insert(name.clone(), declared);
+                    self.trace(format_args!("📦 Asignado mutable: {} := {:?}", name, val));
                 } else {
-                    println!("📦 Asignado: {} := {:?}", name, val);
+                    self.mutables.remove(&name);
+                    self.trace(format_args!("📦 Asignado: {} := {:?}", name, val));
                 }
                 self.env.insert(name, val.clone());
-                val
+                Ok(val)
+            }
+
+            Expr::TypeAlias { name, target } => {
+                self.types.define_alias(&name, &target).map_err(RuntimeError::type_error)?;
+                Ok(Value::Void)
             }
 
             Expr::FunctionDef { name, params, body, return_type } => {
                 let func = Function::UserDefined { name: name.clone(), params, body, return_type };
-                println!("🧠 Función definida por el usuario: {}", name);
+                self.trace(format_args!("🧠 Función definida por el usuario: {}", name));
                 self.functions.insert(name, func);
-                Value::Void
+                Ok(Value::Void)
             }
 
-            Expr::FunctionCall { name, args } => {
-                if let Some(func) = self.functions.get(&name).cloned() {
-                    match func {
-                        Function::Native(f) => {
-                            let evaluated_args = args.into_iter().map(|a| self.eval_expr(a)).collect::<Vec<_>>();
-                            return f(evaluated_args);
-                        }
-                        Function::UserDefined { params, body, .. } => {
-                            if params.len() != args.len() {
-                                println!("⚠️ Número incorrecto de argumentos para '{}'", name);
-                                return Value::Void;
-                            }
-                            let mut local_env = HashMap::new();
-                            for ((param_name, _), arg) in params.into_iter().zip(args.into_iter()) {
-                                let value = self.eval_expr(arg);
-                                local_env.insert(param_name, value);
-                            }
-                            let mut sub = Interpreter {
-                                env: local_env,
-                                functions: self.functions.clone(),
-                                imported_modules: self.imported_modules.clone(),
-                            };
-                            for expr in body {
-                                if let Expr::Return(ret_expr) = expr {
-                                    return sub.eval_expr(*ret_expr);
-                                } else {
-                                    sub.eval(expr);
-                                }
-                            }
+            Expr::FunctionCall { name, args, line } => {
+                let args = args.into_iter().map(|a| self.eval_expr(a)).collect::<Result<Vec<_>, _>>()?;
+                // Una variable que guarda una función tiene prioridad sobre las globales;
+                // llamar a una variable que no es función es un TypeError.
+                let result = match self.env.get(&name).cloned() {
+                    Some(Value::Function(f)) => self.call_function_ref(&f, args),
+                    Some(other) if !self.functions.contains_key(&name) => self.call_value(&other, args),
+                    _ => self.call_named(&name, args),
+                };
+                Ok(result.map_err(|e| e.with_frame(&name, line))?)
+            }
+
+            Expr::Call { callee, args, line } => {
+                let callee = self.eval_expr(*callee)?;
+                let args = args.into_iter().map(|a| self.eval_expr(a)).collect::<Result<Vec<_>, _>>()?;
+                let name = match &callee {
+                    Value::Function(FunctionRef::Named(name)) => name.clone(),
+                    _ => "<fn>".to_string(),
+                };
+                Ok(self.call_value(&callee, args).map_err(|e| e.with_frame(&name, line))?)
+            }
+
+            Expr::Try { body, error_var, handler } => {
+                match self.exec_block(body) {
+                    Err(Control::Error(e)) => {
+                        if let Some(var) = error_var {
+                            self.env.insert(var, error_to_value(&e));
                         }
+                        self.exec_block(handler)
+                    }
+                    other => other,
+                }
+            }
+
+            Expr::Reassign { target, value } => {
+                let val = self.eval_expr(*value)?;
+                self.assign_to(*target, val.clone())?;
+                Ok(val)
+            }
+
+            Expr::Return(value) => {
+                let val = self.eval_expr(*value)?;
+                Err(Control::Return(val))
+            }
+            Expr::Break => Err(Control::Break),
+            Expr::Continue => Err(Control::Continue),
+
+            Expr::If { branches, else_branch } => {
+                for (condition, body) in branches {
+                    let cond = self.eval_expr(condition)?;
+                    if expect_condition("if", cond)? {
+                        return self.exec_block(body);
+                    }
+                }
+                match else_branch {
+                    Some(body) => self.exec_block(body),
+                    None => Ok(Value::Void),
+                }
+            }
+
+            Expr::While { condition, body } => {
+                loop {
+                    let cond = self.eval_expr((*condition).clone())?;
+                    if !expect_condition("while", cond)? {
+                        break;
+                    }
+                    match self.exec_block(body.clone()) {
+                        Ok(_) | Err(Control::Continue) => {}
+                        Err(Control::Break) => break,
+                        Err(other) => return Err(other),
                     }
-                } else {
-                    println!("⚠️ Llamada a función desconocida: {}", name);
                 }
-                Value::Void
+                Ok(Value::Void)
+            }
+
+            Expr::For { var, iterable, body } => {
+                let items = match self.eval_expr(*iterable)? {
+                    Value::List(items) => items,
+                    Value::String(text) => text.chars().map(|c| Value::String(c.to_string())).collect(),
+                    Value::Map(map) => map.keys().map(|k| Value::String(k.clone())).collect(),
+                    other => {
+                        return Err(RuntimeError::type_error(format!(
+                            "No se puede iterar sobre '{}'",
+                            Type::of(&other)
+                        )).into());
+                    }
+                };
+                for item in items {
+                    self.env.insert(var.clone(), item);
+                    match self.exec_block(body.clone()) {
+                        Ok(_) | Err(Control::Continue) => {}
+                        Err(Control::Break) => break,
+                        Err(other) => return Err(other),
+                    }
+                }
+                Ok(Value::Void)
+            }
+
+            _ => self.eval_expr(expr),
+        }
+    }
+
+    fn exec_block(&mut self, body: Vec<Expr>) -> Result<Value, Control> {
+        for stmt in body {
+            self.eval(stmt)?;
+        }
+        Ok(Value::Void)
+    }
+
+    /// Stores `value` into an assignable place: a mutable binding or an
+    /// element of a collection held by one. Collections are values, so
+    /// writing `xs[i][j]` rebuilds `xs[i]` and then `xs`, and stores the
+    /// result back into its binding. Every index is evaluated once, left to
+    /// right, before anything is read or written.
+    fn assign_to(&mut self, target: Expr, value: Value) -> Result<(), Control> {
+        let mut path = Vec::new();
+        let name = self.eval_place(target, &mut path)?;
+        let value = if path.is_empty() {
+            value
+        } else {
+            let Some(container) = self.env.get(&name).cloned() else {
+                return Err(RuntimeError::new(ErrorKind::Name, format!("Variable no definida: {}", name)).into());
+            };
+            set_path(container, &path, value)?
+        };
+        self.assign_binding(name, value)
+    }
+
+    /// The binding an assignable `target` starts from; the indexes that
+    /// lead from it to the element are pushed onto `path`, outermost first.
+    fn eval_place(&mut self, target: Expr, path: &mut Vec<Value>) -> Result<String, Control> {
+        match target {
+            Expr::Identifier(name) => Ok(name),
+            Expr::Index { target, index } => {
+                let name = self.eval_place(*target, path)?;
+                path.push(self.eval_expr(*index)?);
+                Ok(name)
+            }
+            other => Err(RuntimeError::type_error(format!("No se puede asignar a {:?}", other)).into()),
+        }
+    }
+
+    /// Rebinds the mutable variable `name`, checking its declared type.
+    fn assign_binding(&mut self, name: String, value: Value) -> Result<(), Control> {
+        let Some(ty) = self.mutables.get(&name) else {
+            if self.env.contains_key(&name) {
+                return Err(RuntimeError::new(
+                    ErrorKind::Mutability,
+                    format!("'{}' no es mutable; decláralo con 'define mut'", name),
+                ).into());
             }
+            return Err(RuntimeError::new(ErrorKind::Name, format!("Variable no definida: {}", name)).into());
+        };
+        if !ty.accepts(&value) {
+            return Err(RuntimeError::type_error(format!(
+                "'{}' es de tipo '{}' pero se le asignó '{}' ({:?})",
+                name, ty, Type::of(&value), value
+            )).into());
+        }
+        let value = ty.coerce(value);
+        self.env.insert(name, value);
+        Ok(())
+    }
+
+    /// Calls a function value with already evaluated arguments. Natives use
+    /// this to invoke callbacks passed in from Lunaria code.
+    pub fn call_value(&mut self, callee: &Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Function(f) => self.call_function_ref(f, args),
+            other => Err(RuntimeError::type_error(format!(
+                "Se esperaba una función pero se recibió '{}'",
+                Type::of(other)
+            ))),
+        }
+    }
+
+    fn call_function_ref(&mut self, f: &FunctionRef, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        match f {
+            FunctionRef::Named(name) => self.call_named(name, args),
+            FunctionRef::Closure(closure) => self.call_user(
+                "<fn>",
+                &closure.params,
+                &closure.return_type,
+                closure.body.clone(),
+                closure.captured.clone(),
+                args,
+            ),
+        }
+    }
+
+    fn call_named(&mut self, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let Some(func) = self.functions.get(name).cloned() else {
+            return Err(RuntimeError::new(
+                ErrorKind::Name,
+                format!("Llamada a función desconocida: {}", name),
+            ));
+        };
+        match func {
+            Function::Native(f) => f(self, args),
+            Function::UserDefined { params, body, return_type, .. } => {
+                self.call_user(name, &params, &return_type, body, HashMap::new(), args)
+            }
+        }
+    }
+
+    /// Runs a user-defined function body in a fresh environment made of
+    /// `captured` plus the parameters, checking the declared signature.
+    fn call_user(
+        &mut self,
+        name: &str,
+        params: &[(String, TypeExpr)],
+        return_type: &TypeExpr,
+        body: Vec<Expr>,
+        captured: HashMap<String, Value>,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        if params.len() != args.len() {
+            return Err(RuntimeError::new(
+                ErrorKind::Arity,
+                format!("'{}' espera {} argumento(s) pero recibió {}", name, params.len(), args.len()),
+            ));
+        }
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::new(
+                ErrorKind::StackOverflow,
+                format!("Se superó la profundidad máxima de llamadas ({}) en '{}'", self.max_call_depth, name),
+            ));
+        }
+        let mut local_env = captured;
+        for (position, ((param_name, param_type), value)) in params.iter().zip(args).enumerate() {
+            let ty = self.resolve_type(param_type)?;
+            if !ty.accepts(&value) {
+                return Err(RuntimeError::type_error(format!(
+                    "'{}': el argumento {} ('{}') espera '{}' pero recibió '{}' ({:?})",
+                    name, position + 1, param_name, ty, Type::of(&value), value
+                )));
+            }
+            local_env.insert(param_name.clone(), ty.coerce(value));
+        }
+        // El cuerpo corre en este mismo intérprete con un entorno propio.
+        let caller_env = std::mem::replace(&mut self.env, local_env);
+        let caller_mutables = std::mem::take(&mut self.mutables);
+        self.call_depth += 1;
+        let outcome = self.exec_block(body);
+        self.call_depth -= 1;
+        self.env = caller_env;
+        self.mutables = caller_mutables;
+        let result = match outcome {
+            Ok(_) => Value::Void,
+            Err(Control::Return(value)) => value,
+            Err(other) => return Err(other.into_error()),
+        };
+        let ty = self.resolve_type(return_type)?;
+        if !ty.accepts(&result) {
+            return Err(RuntimeError::type_error(format!(
+                "'{}' declara '-> {}' pero devolvió '{}' ({:?})",
+                name, ty, Type::of(&result), result
+            )));
+        }
+        Ok(ty.coerce(result))
+    }
+
+    fn resolve_type(&self, expr: &TypeExpr) -> Result<Type, RuntimeError> {
+        self.types.resolve(expr).map_err(RuntimeError::type_error)
+    }
+
+    /// Writes `message` to stderr when `verbose` is set.
+    fn trace(&self, message: impl std::fmt::Display) {
+        if self.verbose {
+            eprintln!("{}", message);
+        }
+    }
 
-            _ => Value::Void
+    /// Fails once evaluation has used more than `stack_limit` bytes of stack.
+    fn check_stack(&mut self) -> Result<(), RuntimeError> {
+        let here = stack_position();
+        let base = *self.stack_base.get_or_insert(here);
+        if base.abs_diff(here) > self.stack_limit {
+            return Err(RuntimeError::new(
+                ErrorKind::StackOverflow,
+                "Expresiones y llamadas demasiado anidadas para la pila del intérprete",
+            ));
         }
+        Ok(())
     }
 
-    fn eval_expr(&mut self,expr: Expr) -> Value {
+    fn eval_expr(&mut self,expr: Expr) -> Result<Value, Control> {
+        self.check_stack()?;
         match expr {
-            Expr::Identifier(name) => self.env.get(&name).cloned().unwrap_or(Value::Void),
+            Expr::Identifier(name) => {
+                if let Some(value) = self.env.get(&name).or_else(|| self.constants.get(&name)) {
+                    return Ok(value.clone());
+                }
+                // El nombre de una función declarada se puede usar como valor.
+                if self.functions.contains_key(&name) {
+                    return Ok(Value::Function(FunctionRef::Named(name)));
+                }
+                Err(RuntimeError::new(ErrorKind::Name, format!("Variable no definida: {}", name)).into())
+            }
+            Expr::FunctionCall { .. } | Expr::Call { .. } => self.eval(expr),
+            Expr::Lambda { params, return_type, body } => {
+                Ok(Value::Function(FunctionRef::Closure(Rc::new(Closure {
+                    params,
+                    return_type,
+                    body,
+                    captured: self.env.clone(),
+                }))))
+            }
             Expr::MapLiteral(pairs) =>{
-                let mut map = HashMap::new();
-                for(k, v_expr) in pairs{
+                let mut map = OrderedMap::new();
+                for(k_expr, v_expr) in pairs{
+                    let k = expect_key(self.eval_expr(k_expr)?)?;
                     let v=
-                    self.eval_expr(v_expr);
+                    self.eval_expr(v_expr)?;
                         map.insert(k, v);
                 }
-                Value::Map(map)
+                Ok(Value::Map(map))
+            }
+            Expr::List(items) => {
+                let values = items.into_iter().map(|e| self.eval_expr(e)).collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::List(values))
+            }
+            Expr::Index { target, index } => {
+                let container = self.eval_expr(*target)?;
+                let index = self.eval_expr(*index)?;
+                Ok(get_index(container, index)?)
+            }
+            Expr::Slice { target, start, end } => {
+                let container = self.eval_expr(*target)?;
+                let start = start.map(|e| self.eval_expr(*e)).transpose()?;
+                let end = end.map(|e| self.eval_expr(*e)).transpose()?;
+                Ok(slice(container, start, end)?)
+            }
+            Expr::Reassign { .. } => self.eval(expr),
+            Expr::Binary { op, left, right } => {
+                let l = self.eval_expr(*left)?;
+                // `and` / `or` evalúan en cortocircuito
+                if op == "and" || op == "or" {
+                    let l = expect_bool(&op, l)?;
+                    if (op == "and" && !l) || (op == "or" && l) {
+                        return Ok(Value::Boolean(l));
+                    }
+                    let r = self.eval_expr(*right)?;
+                    return Ok(Value::Boolean(expect_bool(&op, r)?));
+                }
+                let r = self.eval_expr(*right)?;
+                Ok(binary_op(&op, l, r)?)
             }
-            Expr::MapAccess {map, key} =>{
-                let m = self.eval_expr(*map);
-                let k = self.eval_expr(*key);
-                if let (Value::Map(map),
-            Value::String(key)) = (m, k) {
-
-                map.get(&key).cloned().unwrap_or(Value::Void)
-            }else{
-                println!("Invalid map access.");
-                Value::Void
+            Expr::Interpolated(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    text.push_str(&stringify(&self.eval_expr(part)?));
                 }
+                Ok(Value::String(text))
             }
-            _ => self.expr_to_value(expr),
+
+            Expr::Unary { op, operand } => {
+                let v = self.eval_expr(*operand)?;
+                match (op.as_str(), v) {
+                    ("-", Value::Integer(n)) => Ok(Value::Integer(n.checked_neg().ok_or_else(|| {
+                        overflow_error(format!("-({})", n))
+                    })?)),
+                    ("-", Value::BigInt(n)) => Ok(Value::BigInt(-&n)),
+                    ("-", Value::Float(f)) => Ok(Value::Float(-f)),
+                    ("not", v) => Ok(Value::Boolean(!expect_bool("not", v)?)),
+                    (op, v) => Err(RuntimeError::type_error(format!(
+                        "El operador '{}' no admite '{}'",
+                        op, Type::of(&v)
+                    )).into()),
+                }
+            }
+            _ => Ok(self.expr_to_value(expr)),
         }
     }
 
     fn expr_to_value(&self, expr: Expr) -> Value {
         match expr {
             Expr::String(s) => Value::String(s),
-            Expr::Number(n) => { if n.fract() == 0.0{
-                Value::Integer(n as i32)
-            }else{
-                Value::Float(n)
-            }
-        }
+            Expr::Integer(n) => Value::Integer(n),
+            Expr::Float(f) => Value::Float(f),
             Expr::Boolean(b) => Value::Boolean(b),
             _ => Value::Void,
         }
     }
 
-    fn entry_point(&mut self) {
-        if let Some(Function::UserDefined { params, .. }) = self.functions.get("main") {
-            if params.is_empty() {
-                println!("📖 Ejecutando desde Grimorium Caelestia...");
-               let _= self.eval(Expr::FunctionCall {
-                    name: "main".to_string(),
-                    args: vec![],
-                });
+    /// Calls `main` if the program defines one. `fn main(args::List<Text>)`
+    /// receives `script_args`; an `int` result becomes the exit code, which
+    /// must be in `0..=255` as on every platform's process exit status.
+    fn entry_point(&mut self) -> Result<i32, RuntimeError> {
+        let Some(Function::UserDefined { params, .. }) = self.functions.get("main") else {
+            return Ok(0);
+        };
+        let args = match params.len() {
+            0 => vec![],
+            1 => {
+                let list = self.script_args.iter().cloned().map(Value::String).collect();
+                vec![Value::List(list)]
             }
+            n => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Arity,
+                    format!("'main' debe recibir cero parámetros o una lista de argumentos, no {}", n),
+                ));
+            }
+        };
+        self.trace("📖 Ejecutando desde Grimorium Caelestia...");
+        match self.call_named("main", args).map_err(|e| e.with_frame("main", 0))? {
+            Value::Integer(code) if (0..=255).contains(&code) => Ok(code as i32),
+            Value::Integer(code) => Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("'main' devolvió {}, pero el código de salida debe estar entre 0 y 255", code),
+            )),
+            Value::Void => Ok(0),
+            other => Err(RuntimeError::type_error(format!(
+                "'main' debe devolver 'Int' o 'Void', no '{}'",
+                Type::of(&other)
+            ))),
         }
     }
 }
 
-fn register_native_functions(interpreter: &mut Interpreter) {
-    fn stringify(val: &Value) -> String {
-        match val {
-            Value::String(s) => s.clone(),
-            Value::Integer(n) => n.to_string(),
-            Value::Float(f) => f.to_string(),
-            Value::Boolean(b) => b.to_string(),
-            Value::Void => "(void)".to_string(),
-            Value::Map(map) => {
-                let items = map.iter()
-                    .map(|(k, v)| format!("'{}': {}", k, stringify(v)))
-                    .collect::<Vec<_>>();
-                format!("{{ {} }}", items.join(", "))
-            }
-            Value::List(list) => {
-                let items = list.iter().map(stringify).collect::<Vec<_>>();
-                format!("[{}]", items.join(", "))
-            }
-        }
-    }    
-    
-    fn console_out(args: Vec<Value>) -> Value {
-        for val in args {
-                print!("{}", stringify(&val));
+/// The value bound by `catch err`: a map with the error's kind, message
+/// and stack trace.
+fn error_to_value(e: &RuntimeError) -> Value {
+    let mut map = OrderedMap::new();
+    map.insert("kind".to_string(), Value::String(e.kind.to_string()));
+    map.insert("message".to_string(), Value::String(e.message.clone()));
+    let trace = e.trace.iter().map(|f| Value::String(f.to_string())).collect();
+    map.insert("trace".to_string(), Value::List(trace));
+    Value::Map(map)
+}
+
+fn expect_condition(context: &str, value: Value) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Boolean(b) => Ok(b),
+        other => Err(RuntimeError::type_error(format!(
+            "La condición de '{}' debe ser 'Bool' pero es '{}'",
+            context, Type::of(&other)
+        ))),
+    }
+}
+
+fn expect_bool(op: &str, value: Value) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Boolean(b) => Ok(b),
+        other => Err(RuntimeError::type_error(format!(
+            "El operador '{}' espera 'Bool' pero recibió '{}'",
+            op, Type::of(&other)
+        ))),
+    }
+}
+
+pub fn binary_op(op: &str, l: Value, r: Value) -> Result<Value, RuntimeError> {
+    use std::cmp::Ordering;
 
+    let mismatch = |l: &Value, r: &Value| {
+        RuntimeError::type_error(format!(
+            "El operador '{}' no admite '{}' y '{}'",
+            op, Type::of(l), Type::of(r)
+        ))
+    };
+
+    match op {
+        "==" => return Ok(Value::Boolean(values_equal(&l, &r))),
+        "!=" => return Ok(Value::Boolean(!values_equal(&l, &r))),
+        "<" | ">" | "<=" | ">=" => {
+            let ordering = match (&l, &r) {
+                (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+                (Value::String(a), Value::String(b)) => a.cmp(b),
+                _ if let (Some(a), Some(b)) = (as_bigint(&l), as_bigint(&r)) => a.cmp(&b),
+                _ => match (as_float(&l), as_float(&r)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b).ok_or_else(|| mismatch(&l, &r))?,
+                    _ => return Err(mismatch(&l, &r)),
+                },
+            };
+            let result = match op {
+                "<" => ordering == Ordering::Less,
+                ">" => ordering == Ordering::Greater,
+                "<=" => ordering != Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            };
+            return Ok(Value::Boolean(result));
         }
-        println!();
-        Value::Void
+        _ => {}
     }
 
-    fn add(args: Vec<Value>) -> Value {
-        if args.len() != 2 {
-            println!("🔍 Argumentos recibidos en add: {:?}", args);
-            match (&args[0], &args[1]) {
-                (Value::Integer(a), Value::Integer(b)) => Value::Integer(a + b),
-                (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
-                (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 + b),
-                (Value::Float(a), Value::Integer(b)) => Value::Float(a + *b as f64),
+    match (&l, &r) {
+        (Value::Integer(a), Value::Integer(b)) => int_op(op, *a, *b),
+        _ if let (Some(a), Some(b)) = (as_bigint(&l), as_bigint(&r)) => {
+            let result = match op {
+                "+" => &a + &b,
+                "-" => &a - &b,
+                "*" => &a * &b,
                 _ => {
-                    println!("❌ 'add' espera números válidos");
-                    Value::Void
+                    let (quotient, remainder) = a
+                        .div_rem(&b)
+                        .ok_or_else(|| RuntimeError::new(ErrorKind::Arithmetic, "División por cero"))?;
+                    if op == "/" { quotient } else { remainder }
                 }
-            }
-        } else {
-            println!("❌ 'add' espera 2 argumentos");
-            Value::Void
+            };
+            Ok(Value::BigInt(result))
         }
-    }
-    fn sub(args: Vec<Value>) -> Value {
-        if args.len() == 2 {
-            match (&args[0], &args[1]) {
-                (Value::Integer(a), Value::Integer(b)) => Value::Integer(a - b),
-                (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
-                (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 - b),
-                (Value::Float(a), Value::Integer(b)) => Value::Float(a - *b as f64),
-                _ => {
-                    println!("❌ 'sub' espera números válidos");
-                    Value::Void
+        _ => match (as_float(&l), as_float(&r)) {
+            (Some(a), Some(b)) => {
+                if matches!(op, "/" | "%") && b == 0.0 {
+                    return Err(RuntimeError::new(ErrorKind::Arithmetic, "División por cero"));
                 }
+                Ok(Value::Float(match op {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" => a / b,
+                    _ => a % b,
+                }))
             }
-        } else {
-            println!("❌ 'sub' espera 2 argumentos");
-            Value::Void
+            _ => Err(mismatch(&l, &r)),
+        },
+    }
+}
+
+/// Checked `Int` arithmetic: overflow raises an `OverflowError` instead of
+/// wrapping or panicking.
+fn int_op(op: &str, a: i64, b: i64) -> Result<Value, RuntimeError> {
+    if matches!(op, "/" | "%") && b == 0 {
+        return Err(RuntimeError::new(ErrorKind::Arithmetic, "División por cero"));
+    }
+    let result = match op {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" => a.checked_div(b),
+        _ => a.checked_rem(b),
+    };
+    result
+        .map(Value::Integer)
+        .ok_or_else(|| overflow_error(format!("{} {} {}", a, op, b)))
+}
+
+pub fn overflow_error(operation: String) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Overflow,
+        format!("Desbordamiento de 'Int' en {} (usa 'BigInt' para enteros sin límite)", operation),
+    )
+}
+
+/// Big-integer view of an integer value, so `Int` operands mix with
+/// `BigInt` ones. Callers handle the plain `Int`/`Int` case first.
+fn as_bigint(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Integer(n) => Some(BigInt::from_i64(*n)),
+        Value::BigInt(n) => Some(n.clone()),
+        _ => None,
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::BigInt(n) => Some(n.to_f64()),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// Address of a local variable, which moves as the native stack grows.
+#[inline(never)]
+fn stack_position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Structural equality, treating `1 == 1.0` as true at any depth: lists
+/// compare element by element and maps key by key, in any order.
+pub fn values_equal(l: &Value, r: &Value) -> bool {
+    match (l, r) {
+        (Value::List(a), Value::List(b)) => a.len() == b.len() && a.iter().zip(b).all(|(x, y)| values_equal(x, y)),
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, x)| b.get(k).is_some_and(|y| values_equal(x, y)))
         }
+        (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => as_float(l) == as_float(r),
+        (Value::BigInt(_), Value::Float(_)) | (Value::Float(_), Value::BigInt(_)) => as_float(l) == as_float(r),
+        (Value::BigInt(_), Value::Integer(_)) | (Value::Integer(_), Value::BigInt(_)) => as_bigint(l) == as_bigint(r),
+        _ => l == r,
     }
+}
 
-fn mul(args: Vec<Value>) -> Value {
-    if args.len() == 2 {
-        match (&args[0], &args[1]) {
-            (Value::Integer(a), Value::Integer(b)) => Value::Integer(a * b),
-            (Value::Float(a), Value::Float(b)) => Value::Float(a * b),
-            (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 * b),
-            (Value::Float(a), Value::Integer(b)) => Value::Float(a * *b as f64),
-            _ => {
-                println!("❌ 'mul' espera números válidos");
-                Value::Void
-            }
+/// Resolves a possibly negative index against a collection of `len` items.
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&resolved).then_some(resolved as usize)
+}
+
+fn index_error(index: i64, len: usize) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Index,
+        format!("Índice {} fuera de rango para una colección de longitud {}", index, len),
+    )
+}
+
+fn expect_index(index: &Value) -> Result<i64, RuntimeError> {
+    match index {
+        Value::Integer(i) => Ok(*i),
+        other => Err(RuntimeError::type_error(format!("Los índices deben ser 'Int', no '{}'", Type::of(other)))),
+    }
+}
+
+fn expect_key(key: Value) -> Result<String, RuntimeError> {
+    match key {
+        Value::String(k) => Ok(k),
+        other => Err(RuntimeError::type_error(format!("Las claves de un mapa deben ser 'Text', no '{}'", Type::of(&other)))),
+    }
+}
+
+fn get_index(container: Value, index: Value) -> Result<Value, RuntimeError> {
+    match (container, index) {
+        (Value::List(items), index) => {
+            let i = expect_index(&index)?;
+            let pos = normalize_index(i, items.len()).ok_or_else(|| index_error(i, items.len()))?;
+            Ok(items[pos].clone())
+        }
+        (Value::String(text), index) => {
+            let i = expect_index(&index)?;
+            let chars = text.chars().collect::<Vec<_>>();
+            let pos = normalize_index(i, chars.len()).ok_or_else(|| index_error(i, chars.len()))?;
+            Ok(Value::String(chars[pos].to_string()))
         }
+        (Value::Map(map), key) => {
+            let key = expect_key(key)?;
+            map.get(&key).cloned().ok_or_else(|| {
+                RuntimeError::new(ErrorKind::Key, format!("La clave '{}' no existe en el mapa", key))
+            })
+        }
+        (container, index) => Err(RuntimeError::type_error(format!(
+            "No se puede indexar '{}' con '{}'",
+            Type::of(&container), Type::of(&index)
+        ))),
+    }
+}
+
+/// `container` with the element at `path` replaced by `value`, rebuilding
+/// each collection on the way from the inside out.
+fn set_path(container: Value, path: &[Value], value: Value) -> Result<Value, RuntimeError> {
+    let Some((index, rest)) = path.split_first() else {
+        return Ok(value);
+    };
+    // En el último nivel el elemento se reemplaza (o la clave se crea) sin leerlo.
+    let value = if rest.is_empty() {
+        value
     } else {
-        println!("❌ 'mul' espera 2 argumentos");
-        Value::Void
+        set_path(get_index(container.clone(), index.clone())?, rest, value)?
+    };
+    set_index(container, index.clone(), value)
+}
+
+fn set_index(container: Value, index: Value, value: Value) -> Result<Value, RuntimeError> {
+    match container {
+        Value::List(mut items) => {
+            let i = expect_index(&index)?;
+            let pos = normalize_index(i, items.len()).ok_or_else(|| index_error(i, items.len()))?;
+            items[pos] = value;
+            Ok(Value::List(items))
+        }
+        Value::Map(mut map) => {
+            map.insert(expect_key(index)?, value);
+            Ok(Value::Map(map))
+        }
+        other => Err(RuntimeError::type_error(format!(
+            "No se puede asignar por índice en '{}'",
+            Type::of(&other)
+        ))),
     }
 }
-    fn div(args: Vec<Value>) -> Value{
-        if args.len() == 2{
-            match(&args[0], &args[1]){
-                (Value::Integer(_),Value::Float(b)) if *b == 0.0 =>{
-                    println!("❌ División por cero no permitida");
-                    Value::Void
-                }
-                (Value::Integer(a), Value::Integer(b)) => Value::Integer(a / b),
-            (Value::Float(a), Value::Float(b)) => Value::Float(a / b),
-            (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 / b),
-            (Value::Float(a), Value::Integer(b)) => Value::Float(a / *b as f64),
-            _ => {
-                println!("❌ 'div' espera números válidos");
-                Value::Void
-            }
+
+/// Python-style slice: bounds may be negative and are clamped to the length.
+pub fn slice(container: Value, start: Option<Value>, end: Option<Value>) -> Result<Value, RuntimeError> {
+    fn bound(value: Option<Value>, default: usize, len: usize) -> Result<usize, RuntimeError> {
+        let Some(value) = value else {
+            return Ok(default);
+        };
+        let i = expect_index(&value)?;
+        let resolved = if i < 0 { len as i64 + i } else { i };
+        Ok(resolved.clamp(0, len as i64) as usize)
+    }
+
+    match container {
+        Value::List(items) => {
+            let from = bound(start, 0, items.len())?;
+            let to = bound(end, items.len(), items.len())?.max(from);
+            Ok(Value::List(items[from..to].to_vec()))
+        }
+        Value::String(text) => {
+            let chars = text.chars().collect::<Vec<_>>();
+            let from = bound(start, 0, chars.len())?;
+            let to = bound(end, chars.len(), chars.len())?.max(from);
+            Ok(Value::String(chars[from..to].iter().collect()))
+        }
+        other => Err(RuntimeError::type_error(format!("No se puede rebanar '{}'", Type::of(&other)))),
+    }
+}
+
+/// How values are shown by `console.out` and inside interpolated strings.
+pub fn stringify(val: &Value) -> String {
+    match val {
+        Value::String(s) => s.clone(),
+        Value::Integer(n) => n.to_string(),
+        Value::BigInt(n) => n.to_string(),
+        Value::Float(f) => format!("{:?}", f),
+        Value::Boolean(b) => b.to_string(),
+        Value::Void => "(void)".to_string(),
+        Value::Map(map) => {
+            let items = map.iter()
+                .map(|(k, v)| format!("'{}': {}", k, stringify(v)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", items.join(", "))
+        }
+        Value::List(list) => {
+            let items = list.iter().map(stringify).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
         }
-    } else{
-        println!("❌ 'div' espera 2 argumentos");
-        Value::Void
+        Value::Function(FunctionRef::Named(name)) => format!("<fn {}>", name),
+        Value::Function(FunctionRef::Closure(_)) => "<fn>".to_string(),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::test_support::{run_on, run_source};
+
+    fn list(items: Vec<Value>) -> Value {
+        Value::List(items)
+    }
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    #[test]
+    fn main_result_is_the_exit_code() {
+        assert_eq!(run_source("fn main() -> int { return 0 }").unwrap(), 0);
+        assert_eq!(run_source("fn main() -> int { return 255 }").unwrap(), 255);
+        assert_eq!(run_source("fn main() -> void { }").unwrap(), 0);
     }
 
-    interpreter.functions.insert("console.out".to_string(), Function::Native(console_out));
-    interpreter.functions.insert("math.add".to_string(), Function::Native(add));
-    interpreter.functions.insert("math.sub".to_string(),Function::Native(sub));
-    interpreter.functions.insert("math.mul".to_string(),Function::Native(mul));
-    interpreter.functions.insert("math.div".to_string(),Function::Native(div));
+    #[test]
+    fn numbers_compare_equal_inside_collections() {
+        let (int, float) = (Value::Integer(1), Value::Float(1.0));
+        assert!(values_equal(&int, &float));
+        assert!(values_equal(&list(vec![int.clone()]), &list(vec![float.clone()])));
+        assert!(values_equal(&map(vec![("a", int.clone())]), &map(vec![("a", float.clone())])));
+        assert!(values_equal(
+            &list(vec![map(vec![("a", list(vec![int.clone()])), ("b", Value::Void)])]),
+            &list(vec![map(vec![("b", Value::Void), ("a", list(vec![float.clone()]))])]),
+        ));
+        assert!(values_equal(&list(vec![Value::BigInt(BigInt::from_i64(1))]), &list(vec![float.clone()])));
+    }
+
+    #[test]
+    fn collections_still_differ_structurally() {
+        let one = Value::Integer(1);
+        assert!(!values_equal(&list(vec![one.clone()]), &list(vec![Value::Float(1.5)])));
+        assert!(!values_equal(&list(vec![one.clone()]), &list(vec![one.clone(), one.clone()])));
+        assert!(!values_equal(&map(vec![("a", one.clone())]), &map(vec![("b", one.clone())])));
+        assert!(!values_equal(&map(vec![("a", one.clone())]), &list(vec![one.clone()])));
+        assert!(!values_equal(&list(vec![]), &map(vec![])));
+    }
+
+    #[test]
+    fn nested_equality_reaches_operators_and_natives() {
+        let source = r#"
+fn main() -> int {
+    if [1] != [1.0] { return 1 }
+    if { "a": 1 } != { "a": 1.0 } { return 2 }
+    if not lists.contains([[1]], [1.0]) { return 3 }
+    if lists.index_of([[0], { "k": [2] }], { "k": [2.0] }) != 1 { return 4 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn each_index_of_an_assignment_is_evaluated_once_in_order() {
+        // `next()` cuenta sus llamadas con el reloj falso: devuelve 0, 1, 2...
+        let source = r#"
+fn next() -> int {
+    time.sleep(1);
+    return time.monotonic() - 1
+}
+fn main() -> int {
+    define mut grid::List<List<int>> := [[0, 0], [0, 0], [0, 0]];
+    grid[next()][next()] := 9;
+    if time.monotonic() != 2 { return 1 }
+    if grid != [[0, 9], [0, 0], [0, 0]] { return 2 }
+    define mut agenda::Map<Text, Map<Text, int>> := { "k0": { "a": 1 }, "k1": {} };
+    agenda["k{next() - 2}"]["b"] := 5;
+    if time.monotonic() != 3 { return 3 }
+    if agenda != { "k0": { "a": 1, "b": 5 }, "k1": {} } { return 4 }
+    return 0
+}
+"#;
+        let mut interpreter = Interpreter::new();
+        interpreter.clock = Clock::fake(0);
+        assert_eq!(run_on(interpreter, source).unwrap(), 0);
+    }
+
+    #[test]
+    fn assigning_through_a_missing_element_fails_without_writing() {
+        let source = r#"
+fn main() -> int {
+    define mut grid::List<List<int>> := [[0]];
+    try { grid[3][0] := 1; return 1 } catch e { if e["kind"] != "IndexError" { return 1 } }
+    define mut agenda::Map<Text, Map<Text, int>> := {};
+    try { agenda["x"]["y"] := 1; return 2 } catch e { if e["kind"] != "KeyError" { return 2 } }
+    define fixed::List<int> := [1];
+    try { fixed[0] := 2; return 3 } catch e { if e["kind"] != "MutabilityError" { return 3 } }
+    if grid != [[0]] or agenda != {} or fixed != [1] { return 4 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn undefined_names_are_name_errors() {
+        let err = run_source("fn main() -> int { console.out(undefined_var); return 0 }").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Name);
+        assert_eq!(err.message, "Variable no definida: undefined_var");
+
+        let source = r#"
+fn twice(x::int) -> int { return x * 2 }
+fn main() -> int {
+    try { define y::int := missing + 1; return 1 } catch e { if e["kind"] != "NameError" { return 1 } }
+    define f::Fn := twice;
+    if f(4) != 8 { return 2 }
+    if lists.map([1], twice) != [2] { return 3 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn declared_types_accept_every_spelling_and_alias() {
+        let source = r#"
+type Name = string;
+type Score = f64;
+fn main() -> int {
+    define who::Name := "Luna";
+    define s::Score := 3;
+    define t::Text := who;
+    define n::i32 := 7;
+    define ok::boolean := true;
+    if s != 3.0 or t != "Luna" or n != 7 or not ok { return 1 }
+    try { define bad::Name := 42; return 2 } catch e { if e["kind"] != "TypeError" { return 2 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+
+        let err = run_source("type Name = Text; fn main() -> int { define n::Name := 1; return 0 }").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Type);
+        assert_eq!(err.message, "'n' se declaró como 'Text' pero recibió 'Int' (Integer(1))");
+        let err = run_source("type int = Text; fn main() -> int { return 0 }").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Type);
+        let err = run_source("fn main() -> int { define x::Nombre := 1; return 0 }").unwrap_err();
+        assert_eq!((err.kind, err.message), (ErrorKind::Type, "Tipo desconocido 'Nombre'".to_string()));
+    }
+
+    #[test]
+    fn generic_annotations_check_every_element() {
+        let source = r#"
+fn main() -> int {
+    define grid::List<List<float>> := [[1, 2]];
+    define scores::Map<Text, Option<float>> := { "a": 1 };
+    if "{grid} {scores}" != "[[1.0, 2.0]] {{ 'a': 1.0 }}" { return 1 }
+    try { define xs::List<int> := [1, "a"]; return 2 } catch e { if e["kind"] != "TypeError" { return 2 } }
+    try { define m::Map<int, int> := {}; return 3 } catch e { if e["kind"] != "TypeError" { return 3 } }
+    try { define n::int<Text> := 1; return 4 } catch e { if e["kind"] != "TypeError" { return 4 } }
+    define mut rows::List<List<int>> := [[1]];
+    try { rows := [[1], ["x"]]; return 5 } catch e { if e["kind"] != "TypeError" { return 5 } }
+    if rows != [[1]] { return 6 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+
+        let err = run_source("fn main() -> int { define xs::List<int> := [1, \"a\"]; return 0 }").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Type);
+        assert_eq!(
+            err.message,
+            "'xs' se declaró como 'List<Int>' pero recibió 'List<Any>' (List([Integer(1), String(\"a\")]))"
+        );
+    }
+
+    #[test]
+    fn arguments_and_results_are_checked_against_the_signature() {
+        let source = r#"
+fn greet(name::Text, times::int) -> void { }
+fn answer() -> int { return "42" }
+fn nothing() -> void { return 1 }
+fn forgets() -> int { }
+fn total(xs::List<int>) -> int { return 0 }
+fn main() -> int {
+    {call}
+    return 0
+}
+"#;
+        let cases = [
+            ("greet(\"a\", \"b\")", "'greet': el argumento 2 ('times') espera 'Int' pero recibió 'Text' (String(\"b\"))"),
+            ("greet(1, 2)", "'greet': el argumento 1 ('name') espera 'Text' pero recibió 'Int' (Integer(1))"),
+            ("answer()", "'answer' declara '-> Int' pero devolvió 'Text' (String(\"42\"))"),
+            ("nothing()", "'nothing' declara '-> Void' pero devolvió 'Int' (Integer(1))"),
+            ("forgets()", "'forgets' declara '-> Int' pero devolvió 'Void' (Void)"),
+            ("total([1, \"a\"])", "'total': el argumento 1 ('xs') espera 'List<Int>' pero recibió 'List<Any>' (List([Integer(1), String(\"a\")]))"),
+        ];
+        for (call, message) in cases {
+            let err = run_source(&source.replace("{call}", call)).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Type, "{}", call);
+            assert_eq!(err.message, message);
+        }
+        let err = run_source(&source.replace("{call}", "greet(\"a\")")).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Arity);
+    }
+
+    #[test]
+    fn accepted_arguments_are_coerced_to_the_declared_type() {
+        let source = r#"
+fn half(x::float) -> float { return x / 2 }
+fn widen(n::int) -> BigInt { return n }
+fn main() -> int {
+    if half(3) != 1.5 { return 1 }
+    if "{widen(2) * widen(4611686018427387904)}" != "9223372036854775808" { return 2 }
+    define shout::Fn := fn(s::Text) -> Text { return s };
+    try { shout(1); return 3 } catch e { if e["kind"] != "TypeError" { return 3 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn functions_are_values() {
+        let source = r#"
+fn make_adder(n::int) -> Fn {
+    return fn(x::int) -> int { return x + n }
+}
+fn apply(f::Fn, x::int) -> int { return f(x) }
+fn double(x::int) -> int { return x * 2 }
+fn main() -> int {
+    define add2::Fn := make_adder(2);
+    if add2(3) != 5 or make_adder(10)(1) != 11 or apply(add2, 5) != 7 { return 1 }
+    if apply(double, 4) != 8 { return 2 }
+    define ops::Map<Text, Fn> := { "inc": fn(x::int) -> int { return x + 1 }, "twice": double };
+    define fs::List<Fn> := [add2, make_adder];
+    if ops["inc"](1) != 2 or ops["twice"](3) != 6 or fs[1](5)(1) != 6 { return 3 }
+    if lists.map([1, 2], add2) != [3, 4] { return 4 }
+    if lists.reduce([1, 2, 3], fn(a::int, b::int) -> int { return a + b }, 0) != 6 { return 5 }
+    define untyped::Fn := fn(x::int) { return "{x}!" };
+    if untyped(4) != "4!" { return 6 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn closures_capture_the_environment_when_created() {
+        let source = r#"
+fn main() -> int {
+    define mut base::int := 1;
+    define get::Fn := fn() -> int { return base };
+    base := 100;
+    if get() != 1 { return 1 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+
+        // Lo capturado es una copia de solo lectura.
+        let source = r#"
+fn main() -> int {
+    define mut counter::int := 0;
+    define bump::Fn := fn() -> int {
+        counter := counter + 1;
+        return counter
+    };
+    bump();
+    return 0
+}
+"#;
+        let err = run_source(source).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Mutability);
+        let lines = err.trace.iter().map(|frame| (frame.function.as_str(), frame.line)).collect::<Vec<_>>();
+        assert_eq!(lines, [("bump", 8), ("main", 0)]);
+    }
+
+    #[test]
+    fn calling_something_that_is_not_a_function_fails() {
+        let cases = [
+            ("define k::int := 3; k(1)", ErrorKind::Type, "Se esperaba una función pero se recibió 'Int'"),
+            ("define xs::List<int> := [1]; xs[0](1)", ErrorKind::Type, "Se esperaba una función pero se recibió 'Int'"),
+            ("missing(1)", ErrorKind::Name, "Llamada a función desconocida: missing"),
+            ("define f::Fn := fn(x::int) -> int { return x }; f(1, 2)", ErrorKind::Arity, "'<fn>' espera 1 argumento(s) pero recibió 2"),
+        ];
+        for (body, kind, message) in cases {
+            let err = run_source(&format!("fn main() -> int {{ {}; return 0 }}", body)).unwrap_err();
+            assert_eq!((err.kind, err.message.as_str()), (kind, message), "{}", body);
+        }
+    }
+
+    #[test]
+    fn return_unwinds_from_nested_blocks() {
+        let source = r#"
+fn find(xs::List<int>, wanted::int) -> int {
+    for i in lists.range(lists.len(xs)) {
+        while true {
+            try {
+                if xs[i] == wanted { return i } else { break }
+            } catch e { return -2 }
+        }
+    }
+    return -1
+}
+fn main() -> int {
+    if find([5, 6, 7], 7) != 2 or find([5], 9) != -1 { return 1 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn break_and_continue_affect_the_innermost_loop() {
+        let source = r#"
+fn main() -> int {
+    define mut seen::List<Text> := [];
+    for i in [1, 2, 3] {
+        define mut j::int := 0;
+        while true {
+            j := j + 1;
+            if j == 2 { continue }
+            if j > 3 { break }
+            seen := lists.push(seen, "{i}{j}");
+        }
+        if i == 2 { break }
+    }
+    if seen != ["11", "13", "21", "23"] { return 1 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn control_flow_outside_its_construct_is_an_error() {
+        let cases = [
+            ("return 1;", "'return' fuera de una función"),
+            ("break;", "'break' fuera de un bucle"),
+            ("continue;", "'continue' fuera de un bucle"),
+            ("fn main() -> int { if true { break } return 0 }", "'break' fuera de un bucle"),
+            // Un `break` dentro de una función no sale del bucle de quien la llama.
+            ("fn stop() -> void { break }\nfn main() -> int { while true { stop() } return 0 }", "'break' fuera de un bucle"),
+        ];
+        for (source, message) in cases {
+            let err = run_source(source).unwrap_err();
+            assert_eq!((err.kind, err.message.as_str()), (ErrorKind::Control, message), "{}", source);
+        }
+    }
+
+    #[test]
+    fn exit_codes_outside_a_byte_are_errors() {
+        for code in ["256", "300", "4294967296", "-1"] {
+            let source = format!("fn main() -> int {{ return {} }}", code);
+            let err = run_source(&source).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Value, "código {}", code);
+        }
+    }
 }
@@ -1,31 +1,141 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Identifier(String),
-    Number(f64),
+    Integer(i64),
+    Float(f64),
     String(String),
+    /// A double-quoted string containing `{expression}` placeholders.
+    Interpolated(Vec<StringPart>),
     Operator(String),
     Symbol(String),
     Keyword(String),
     Boolean(bool),
     Comment(String),
     Newline,
-    EOF,
+    Eof,
+}
+
+/// A piece of an interpolated string: literal text, or the tokens of an
+/// embedded `{expression}` together with their source spans.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Code { tokens: Vec<Token>, spans: Vec<Span> },
+}
+
+/// Where a token or a lexical error sits in the source: 1-based line and
+/// column of its first character, and how many characters it covers on
+/// that line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "❌ {} (line {}, column {})", self.message, self.span.line, self.span.column)
+    }
+}
+
+/// A position in the source, used as the start of a `Span`.
+#[derive(Debug, Clone, Copy)]
+struct Pos {
+    line: usize,
+    column: usize,
+}
+
+/// A peekable character stream that keeps track of the current line and
+/// column.
+struct Cursor<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// A cursor whose first character sits at `start`, for lexing a piece
+    /// of a larger source.
+    fn at(input: &'a str, start: Pos) -> Self {
+        Cursor { chars: input.chars().peekable(), line: start.line, column: start.column }
+    }
+
+    fn pos(&self) -> Pos {
+        Pos { line: self.line, column: self.column }
+    }
+
+    /// The span of everything consumed since `start` (at least one
+    /// character).
+    fn span(&self, start: Pos) -> Span {
+        let len = if self.line == start.line { self.column.saturating_sub(start.column).max(1) } else { 1 };
+        Span { line: start.line, column: start.column, len }
+    }
+
+    /// An error covering everything consumed since `start`.
+    fn error(&self, start: Pos, message: impl Into<String>) -> LexError {
+        LexError { message: message.into(), span: self.span(start) }
+    }
+
+    /// The character after the next one.
+    fn peek_second(&self) -> Option<char> {
+        let mut lookahead = self.chars.clone();
+        lookahead.next();
+        lookahead.next()
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
 }
 
 pub struct Lexer<'a> {
     input: &'a str,
+    /// Source span of each token produced by the last `tokenize` call.
+    pub spans: Vec<Span>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        Self { input }
+        Self { input, spans: Vec::new() }
     }
 
-    pub fn tokenize(&mut self) -> Vec<Token> {
+    /// Splits the source into tokens, or reports every lexical error found.
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, Vec<LexError>> {
+        self.tokenize_at(Pos { line: 1, column: 1 })
+    }
+
+    fn tokenize_at(&mut self, start: Pos) -> Result<Vec<Token>, Vec<LexError>> {
         let mut tokens = Vec::new();
-        let mut chars = self.input.chars().peekable();
+        let mut errors = Vec::new();
+        let mut chars = Cursor::at(self.input, start);
+        self.spans.clear();
 
         while let Some(&ch) = chars.peek() {
+            let start = chars.pos();
             match ch {
                 c if c.is_whitespace() => {
                     chars.next();
@@ -49,16 +159,25 @@ impl<'a> Lexer<'a> {
                         Some('<') => {
                             chars.next();
                             let mut comment = String::new();
+                            let mut closed = false;
                             while let Some(c) = chars.next() {
                                 if c == '>' && chars.peek() == Some(&'~') {
                                     chars.next();
+                                    closed = true;
                                     break;
                                 }
                                 comment.push(c);
                             }
-                            tokens.push(Token::Comment(comment));
+                            if closed {
+                                tokens.push(Token::Comment(comment));
+                            } else {
+                                errors.push(LexError {
+                                    message: "Unterminated '~<' comment (close it with '>~')".to_string(),
+                                    span: Span { line: start.line, column: start.column, len: 2 },
+                                });
+                            }
                         }
-                        _ => {}
+                        _ => errors.push(chars.error(start, "Unexpected '~' (comments start with '~>' or '~<')")),
                     }
                 }
 
@@ -70,6 +189,41 @@ impl<'a> Lexer<'a> {
                     } else if chars.peek() == Some(&':') {
                         chars.next();
                         tokens.push(Token::Symbol("::".to_string()));
+                    } else {
+                        tokens.push(Token::Symbol(":".to_string()));
+                    }
+                }
+                '=' => {
+                    chars.next();
+                    if chars.peek() == Some(&'=') {
+                        chars.next();
+                        tokens.push(Token::Operator("==".to_string()));
+                    } else {
+                        tokens.push(Token::Operator("=".to_string()));
+                    }
+                }
+                '+' | '*' | '/' | '%' => {
+                    tokens.push(Token::Operator(ch.to_string()));
+                    chars.next();
+                }
+
+                '!' => {
+                    chars.next();
+                    if chars.peek() == Some(&'=') {
+                        chars.next();
+                        tokens.push(Token::Operator("!=".to_string()));
+                    } else {
+                        errors.push(chars.error(start, "Unexpected '!' (use '!=' or 'not')"));
+                    }
+                }
+
+                '<' | '>' => {
+                    chars.next();
+                    if chars.peek() == Some(&'=') {
+                        chars.next();
+                        tokens.push(Token::Operator(format!("{}=", ch)));
+                    } else {
+                        tokens.push(Token::Operator(ch.to_string()));
                     }
                 }
                 // -> symbol
@@ -83,62 +237,25 @@ impl<'a> Lexer<'a> {
                     }
                 }
 
-                '"' | '\'' => {
-                    let quote = chars.next().unwrap();
-                    let mut value = String::new();
-                    while let Some(c) = chars.next() {
-                        if c == '\\' && quote == '"' {
-                            if let Some(escaped) = chars.next() {
-                                match escaped {
-                                    'n' => value.push('\n'),
-                                    't' => value.push('\t'),
-                                    'r' => value.push('\r'),
-                                    '\\' => value.push('\\'),
-                                    '"' => value.push('"'),
-                                    _ => {
-                                        value.push('\\');
-                                        value.push(escaped);
-                                    }
-                                }
-                            }
-                        } else if c == quote {
-                            break;
-                        } else {
-                            value.push(c);
-                        }
-                    }
-                    tokens.push(Token::String(value));
-                }
+                '"' | '\'' => match lex_string(&mut chars) {
+                    Ok(token) => tokens.push(token),
+                    Err(e) => errors.push(e),
+                },
 
-                '.' | ',' | '(' | ')' | ';' | '{' | '}' => {
+                'r' if starts_raw_string(&chars) => match lex_raw_string(&mut chars) {
+                    Ok(token) => tokens.push(token),
+                    Err(e) => errors.push(e),
+                },
+
+                '.' | ',' | '(' | ')' | ';' | '{' | '}' | '[' | ']' => {
                     tokens.push(Token::Symbol(ch.to_string()));
                     chars.next();
                 }
 
-                c if c.is_ascii_digit() => {
-                    let mut num_str = String::new();
-                    let mut is_float = false;
-                
-                    while let Some(&c) = chars.peek() {
-                        if c.is_ascii_digit() {
-                            num_str.push(c);
-                            chars.next();
-                        } else if c == '.' && !is_float {
-                            is_float = true;
-                            num_str.push(c);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                
-                    if let Ok(num) = num_str.parse::<f64>() {
-                        tokens.push(Token::Number(num));
-                    } else {
-                        println!("⚠️ Número mal formado: {}", num_str);
-                    }
-                }
-                
+                c if c.is_ascii_digit() => match lex_number(&mut chars) {
+                    Ok(token) => tokens.push(token),
+                    Err(e) => errors.push(e),
+                },
 
                 c if c.is_alphanumeric() || c == '_' => {
                     let mut ident = String::new();
@@ -152,7 +269,8 @@ impl<'a> Lexer<'a> {
                     }
 
                     match ident.as_str() {
-                       "fn" | "define" | "mut" | "return" | "evoke" | "summon" | "as" => {
+                       "fn" | "define" | "mut" | "return" | "evoke" | "summon" | "as" | "type"
+                        | "while" | "for" | "in" | "break" | "continue" | "try" | "catch" => {
                             tokens.push(Token::Keyword(ident));
                         }
                         "true" => tokens.push(Token::Boolean(true)),
@@ -164,12 +282,752 @@ impl<'a> Lexer<'a> {
                     }
                 }
 
-                _ => {
-                    chars.next(); // skip unrecognized
+                other => {
+                    chars.next();
+                    errors.push(chars.error(start, format!("Unexpected character '{}'", other)));
                 }
             }
+            self.spans.resize(tokens.len(), chars.span(start));
         }
 
-        tokens
+        if errors.is_empty() { Ok(tokens) } else { Err(errors) }
+    }
+}
+
+/// Lexes a quoted string. Double-quoted strings (including `"""`
+/// multiline ones) support `{expression}` interpolation; single-quoted
+/// strings only support escapes.
+fn lex_string(chars: &mut Cursor) -> Result<Token, LexError> {
+    let start = chars.pos();
+    let quote = chars.next().unwrap();
+    if quote == '"' && chars.peek() == Some(&'"') && chars.peek_second() == Some('"') {
+        chars.next();
+        chars.next();
+        return lex_triple_string(chars, start);
+    }
+    lex_string_body(chars, Some(quote), quote == '"', start)
+}
+
+/// Reads string contents up to the closing `quote` (or to the end of input
+/// when `quote` is `None`), decoding escapes and, if `interpolate` is set,
+/// `{...}` placeholders. `start` is where the literal opened.
+fn lex_string_body(chars: &mut Cursor, quote: Option<char>, interpolate: bool, start: Pos) -> Result<Token, LexError> {
+    let mut parts = Vec::new();
+    let mut value = String::new();
+    // Tras un error se sigue leyendo hasta la comilla de cierre, para que el
+    // resto del texto no se confunda con código.
+    let mut error = None;
+    loop {
+        let at = chars.pos();
+        let Some(c) = chars.next() else {
+            if quote.is_some() {
+                // Un '{' sin cerrar ya se ha tragado la comilla final.
+                return Err(error.unwrap_or_else(|| unterminated_string(start)));
+            }
+            break;
+        };
+        if Some(c) == quote {
+            break;
+        } else if c == '\\' {
+            match lex_escape(chars, at, start) {
+                Ok(decoded) => value.push(decoded),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        } else if interpolate && (c == '{' || c == '}') {
+            // `{{` y `}}` escriben una llave literal.
+            if chars.peek() == Some(&c) {
+                chars.next();
+                value.push(c);
+            } else if c == '}' {
+                error.get_or_insert(chars.error(at, "Unmatched '}' in string (write '}}' for a literal brace)"));
+            } else {
+                parts.push(StringPart::Text(std::mem::take(&mut value)));
+                match lex_interpolation(chars, at) {
+                    Ok(part) => parts.push(part),
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                }
+            }
+        } else {
+            value.push(c);
+        }
+    }
+    if let Some(e) = error {
+        return Err(e);
+    }
+    if parts.is_empty() {
+        return Ok(Token::String(value));
+    }
+    parts.push(StringPart::Text(value));
+    parts.retain(|part| !matches!(part, StringPart::Text(text) if text.is_empty()));
+    Ok(Token::Interpolated(parts))
+}
+
+/// Decodes the escape sequence after the backslash at `at`.
+fn lex_escape(chars: &mut Cursor, at: Pos, start: Pos) -> Result<char, LexError> {
+    let Some(escaped) = chars.next() else {
+        return Err(unterminated_string(start));
+    };
+    Ok(match escaped {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' => '\\',
+        '"' => '"',
+        '\'' => '\'',
+        'u' => {
+            let mut hex = String::new();
+            let mut valid = chars.next() == Some('{');
+            while valid {
+                match chars.peek() {
+                    Some('}') => {
+                        chars.next();
+                        break;
+                    }
+                    Some(&c) if c.is_ascii_hexdigit() && hex.len() < 6 => {
+                        hex.push(c);
+                        chars.next();
+                    }
+                    _ => valid = false,
+                }
+            }
+            let decoded = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+            match decoded {
+                Some(c) if valid => c,
+                _ => return Err(chars.error(at, "Invalid unicode escape (expected something like '\\u{1F319}')")),
+            }
+        }
+        other => return Err(chars.error(at, format!("Unknown escape sequence '\\{}'", other))),
+    })
+}
+
+/// Lexes a `"""` string after its opening quotes. The text is dedented
+/// before escapes and placeholders are decoded: a line break right after
+/// the opening quotes and the indentation before the closing ones are
+/// dropped, and the leading whitespace shared by the remaining lines is
+/// stripped (a tab and a space are never the same indentation).
+fn lex_triple_string(chars: &mut Cursor, start: Pos) -> Result<Token, LexError> {
+    let mut raw = String::new();
+    loop {
+        let Some(c) = chars.next() else {
+            return Err(unterminated_string(start));
+        };
+        if c == '\\' {
+            raw.push(c);
+            if let Some(escaped) = chars.next() {
+                raw.push(escaped);
+            }
+        } else if c == '"' && chars.peek() == Some(&'"') && chars.peek_second() == Some('"') {
+            chars.next();
+            chars.next();
+            break;
+        } else {
+            raw.push(c);
+        }
+    }
+
+    // Tras quitar la sangría, las columnas dentro del texto son aproximadas.
+    let (text, skipped_first_line) = dedent(&raw);
+    let first = if skipped_first_line {
+        Pos { line: start.line + 1, column: 1 }
+    } else {
+        Pos { line: start.line, column: start.column + 3 }
+    };
+    let mut inner = Cursor::at(&text, first);
+    lex_string_body(&mut inner, None, true, start)
+}
+
+/// Strips the layout of a triple-quoted string (see `lex_triple_string`).
+/// Also reports whether the opening line was dropped.
+fn dedent(raw: &str) -> (String, bool) {
+    let mut body = raw;
+    let mut skipped_first_line = false;
+    if let Some(newline) = body.find('\n')
+        && body[..newline].trim().is_empty()
+    {
+        body = &body[newline + 1..];
+        skipped_first_line = true;
+    }
+    if let Some(newline) = body.rfind('\n')
+        && body[newline + 1..].trim().is_empty()
+    {
+        body = body[..newline].strip_suffix('\r').unwrap_or(&body[..newline]);
+    }
+
+    fn indent_of(line: &str) -> &str {
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+    // La sangría es ASCII, así que el prefijo común cae en un límite de carácter.
+    let shared = |a: &str, b: &str| a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    let indent = body
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(indent_of)
+        .reduce(|common, indent| &common[..shared(common, indent)])
+        .unwrap_or("");
+    let lines = body
+        .split('\n')
+        .map(|line| &line[shared(indent, line)..])
+        .collect::<Vec<_>>();
+    (lines.join("\n"), skipped_first_line)
+}
+
+/// Whether the cursor, sitting on an `r`, starts `r"..."` or `r#"..."#`.
+fn starts_raw_string(chars: &Cursor) -> bool {
+    let mut lookahead = chars.chars.clone();
+    lookahead.next();
+    lookahead.find(|&c| c != '#') == Some('"')
+}
+
+/// Lexes a raw string: no escapes and no interpolation. Any number of `#`
+/// may surround the quotes so the text itself can contain `"`.
+fn lex_raw_string(chars: &mut Cursor) -> Result<Token, LexError> {
+    let start = chars.pos();
+    chars.next();
+    let mut hashes = 0;
+    while chars.peek() == Some(&'#') {
+        chars.next();
+        hashes += 1;
+    }
+    chars.next();
+
+    let mut value = String::new();
+    loop {
+        let Some(c) = chars.next() else {
+            return Err(unterminated_string(start));
+        };
+        if c == '"' {
+            let mut closing = 0;
+            while closing < hashes && chars.peek() == Some(&'#') {
+                chars.next();
+                closing += 1;
+            }
+            if closing == hashes {
+                return Ok(Token::String(value));
+            }
+            value.push('"');
+            value.extend(std::iter::repeat_n('#', closing));
+        } else {
+            value.push(c);
+        }
+    }
+}
+
+fn unterminated_string(start: Pos) -> LexError {
+    LexError {
+        message: "Unterminated string".to_string(),
+        span: Span { line: start.line, column: start.column, len: 1 },
+    }
+}
+
+/// Lexes the expression of a `{...}` placeholder, whose opening brace at
+/// `open` has already been consumed. Nested braces and quoted strings are
+/// allowed, so `"{m["key"]}"` and `"{f({a: 1})}"` work.
+fn lex_interpolation(chars: &mut Cursor, open: Pos) -> Result<StringPart, LexError> {
+    let first = chars.pos();
+    let mut source = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    loop {
+        let Some(c) = chars.next() else {
+            return Err(LexError {
+                message: "Unterminated '{' in string".to_string(),
+                span: Span { line: open.line, column: open.column, len: 1 },
+            });
+        };
+        match quote {
+            Some(q) => {
+                if c == '\\' && q == '"' {
+                    source.push(c);
+                    if let Some(escaped) = chars.next() {
+                        source.push(escaped);
+                    }
+                    continue;
+                }
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            },
+        }
+        source.push(c);
+    }
+    if source.trim().is_empty() {
+        return Err(chars.error(open, "Empty '{}' in string"));
+    }
+
+    let mut lexer = Lexer::new(&source);
+    let tokens = lexer.tokenize_at(first).map_err(|mut errors| errors.remove(0))?;
+    Ok(StringPart::Code { tokens, spans: lexer.spans })
+}
+
+/// Lexes a numeric literal: decimal integers and floats (with optional
+/// fraction and exponent), `0x`/`0o`/`0b` integers, and `_` separators
+/// between digits.
+fn lex_number(chars: &mut Cursor) -> Result<Token, LexError> {
+    let start = chars.pos();
+    let mut text = String::new();
+    let malformed = |text: &str, reason: &str| LexError {
+        message: format!("Malformed number '{}': {}", text, reason),
+        span: Span { line: start.line, column: start.column, len: text.chars().count() },
+    };
+
+    fn take_digits(chars: &mut Cursor, text: &mut String, radix: u32) {
+        while let Some(&c) = chars.peek() {
+            if c.is_digit(radix) || c == '_' {
+                text.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    take_digits(chars, &mut text, 10);
+
+    let radix = match (text.as_str(), chars.peek()) {
+        ("0", Some('x' | 'X')) => 16,
+        ("0", Some('o' | 'O')) => 8,
+        ("0", Some('b' | 'B')) => 2,
+        _ => 10,
+    };
+
+    if radix != 10 {
+        text.push(chars.next().unwrap());
+        let digits_from = text.len();
+        take_digits(chars, &mut text, radix);
+        reject_trailing(chars, &mut text).map_err(|reason| malformed(&text, &reason))?;
+        let digits = &text[digits_from..];
+        check_separators(digits).map_err(|reason| malformed(&text, reason))?;
+        if digits.is_empty() {
+            return Err(malformed(&text, "expected digits after the prefix"));
+        }
+        return i64::from_str_radix(&digits.replace('_', ""), radix)
+            .map(Token::Integer)
+            .map_err(|_| malformed(&text, "too large for Int"));
+    }
+
+    let mut is_float = false;
+    if chars.peek() == Some(&'.') {
+        let mut lookahead = chars.chars.clone();
+        lookahead.next();
+        if lookahead.peek().is_some_and(|c| c.is_ascii_digit()) {
+            is_float = true;
+            text.push('.');
+            chars.next();
+            take_digits(chars, &mut text, 10);
+        }
+    }
+    if let Some(&e @ ('e' | 'E')) = chars.peek() {
+        is_float = true;
+        text.push(e);
+        chars.next();
+        if let Some(&sign @ ('+' | '-')) = chars.peek() {
+            text.push(sign);
+            chars.next();
+        }
+        let digits_from = text.len();
+        take_digits(chars, &mut text, 10);
+        if digits_from == text.len() {
+            return Err(malformed(&text, "expected digits in the exponent"));
+        }
+    }
+    reject_trailing(chars, &mut text).map_err(|reason| malformed(&text, &reason))?;
+    for part in text.split(['.', 'e', 'E', '+', '-']) {
+        check_separators(part).map_err(|reason| malformed(&text, reason))?;
+    }
+
+    let clean = text.replace('_', "");
+    if is_float {
+        match clean.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(Token::Float(f)),
+            _ => Err(malformed(&text, "out of range for Float")),
+        }
+    } else {
+        clean.parse::<i64>().map(Token::Integer).map_err(|_| {
+            malformed(&text, "too large for Int (use bigint(\"...\") for unbounded integers)")
+        })
+    }
+}
+
+/// A number must not run straight into letters, digits of another base or
+/// a second decimal point, as in `12abc`, `0b102` or `1.2.3`. The rest of
+/// such a literal is consumed so the error covers all of it.
+fn reject_trailing(chars: &mut Cursor, text: &mut String) -> Result<(), String> {
+    let continues = |chars: &Cursor| {
+        let mut lookahead = chars.chars.clone();
+        match lookahead.next() {
+            Some('.') => lookahead.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_alphanumeric() || c == '_',
+            None => false,
+        }
+    };
+    if !continues(chars) {
+        return Ok(());
+    }
+    let unexpected = *chars.peek().unwrap();
+    while continues(chars) {
+        text.push(chars.next().unwrap());
+    }
+    Err(format!("unexpected '{}'", unexpected))
+}
+
+/// `_` may only appear between two digits.
+fn check_separators(digits: &str) -> Result<(), &'static str> {
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err("'_' must sit between digits");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LexError, Lexer, Span, StringPart, Token};
+
+    fn tokens(source: &str) -> Vec<Token> {
+        Lexer::new(source).tokenize().unwrap_or_else(|errors| panic!("{:?} no debería fallar: {:?}", source, errors))
+    }
+
+    fn lex_errors(source: &str) -> Vec<LexError> {
+        match Lexer::new(source).tokenize() {
+            Ok(tokens) => panic!("{:?} debería fallar, pero dio {:?}", source, tokens),
+            Err(errors) => errors,
+        }
+    }
+
+    fn span(line: usize, column: usize, len: usize) -> Span {
+        Span { line, column, len }
+    }
+
+    fn string(source: &str) -> String {
+        match tokens(source).as_slice() {
+            [Token::String(s)] => s.clone(),
+            other => panic!("{:?} debería ser un texto, pero dio {:?}", source, other),
+        }
+    }
+
+    fn text(s: &str) -> StringPart {
+        StringPart::Text(s.to_string())
+    }
+
+    /// The tokens of a placeholder, ignoring their spans.
+    fn code(source: &str) -> StringPart {
+        StringPart::Code { tokens: tokens(source), spans: Vec::new() }
+    }
+
+    /// The parts of the only token in `source`, with the placeholder spans
+    /// cleared so they can be compared with `code`.
+    fn parts(source: &str) -> Vec<StringPart> {
+        match tokens(source).as_slice() {
+            [Token::Interpolated(parts)] => parts
+                .iter()
+                .cloned()
+                .map(|part| match part {
+                    StringPart::Code { tokens, .. } => StringPart::Code { tokens, spans: Vec::new() },
+                    text => text,
+                })
+                .collect(),
+            other => panic!("{:?} debería ser un texto interpolado, pero dio {:?}", source, other),
+        }
+    }
+
+    #[test]
+    fn integers_and_floats_are_told_apart() {
+        assert_eq!(tokens("42 4.0 1e3 2.5E-2"), [Token::Integer(42), Token::Float(4.0), Token::Float(1000.0), Token::Float(0.025)]);
+        // `1.abs` es una llamada de método, no un número con decimales.
+        assert_eq!(tokens("1.abs")[..2], [Token::Integer(1), Token::Symbol(".".to_string())]);
+    }
+
+    #[test]
+    fn radix_prefixes_and_separators() {
+        assert_eq!(
+            tokens("0xFF 0Xff 0o17 0b1010 0B1 1_000_000 0b1010_0101 1_0.2_5"),
+            [
+                Token::Integer(255),
+                Token::Integer(255),
+                Token::Integer(15),
+                Token::Integer(10),
+                Token::Integer(1),
+                Token::Integer(1_000_000),
+                Token::Integer(0b1010_0101),
+                Token::Float(10.25),
+            ]
+        );
+        assert_eq!(tokens("0x7FFF_FFFF_FFFF_FFFF"), [Token::Integer(i64::MAX)]);
+    }
+
+    #[test]
+    fn malformed_numbers_are_reported_whole() {
+        let cases = [
+            ("12abc", "Malformed number '12abc': unexpected 'a'"),
+            ("0b102", "Malformed number '0b102': unexpected '2'"),
+            ("0xfg", "Malformed number '0xfg': unexpected 'g'"),
+            ("1.2.3", "Malformed number '1.2.3': unexpected '.'"),
+            ("0x", "Malformed number '0x': expected digits after the prefix"),
+            ("1e", "Malformed number '1e': expected digits in the exponent"),
+            ("1__0", "Malformed number '1__0': '_' must sit between digits"),
+            ("1_", "Malformed number '1_': '_' must sit between digits"),
+            ("0x_", "Malformed number '0x_': '_' must sit between digits"),
+            ("0x_ff", "Malformed number '0x_ff': '_' must sit between digits"),
+        ];
+        for (source, message) in cases {
+            let errors = lex_errors(source);
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].message, message);
+            assert_eq!(errors[0].span, span(1, 1, source.len()), "{}", source);
+        }
+
+        let errors = lex_errors("define x := 3;\n  print(12abc + 1);");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, span(2, 9, 5));
+    }
+
+    #[test]
+    fn integer_overflow_suggests_bigint() {
+        let errors = lex_errors("9223372036854775808");
+        assert_eq!(errors[0].message, "Malformed number '9223372036854775808': too large for Int (use bigint(\"...\") for unbounded integers)");
+        assert_eq!(errors[0].span, span(1, 1, 19));
+        assert_eq!(tokens("9223372036854775807"), [Token::Integer(i64::MAX)]);
+
+        let errors = lex_errors("0x1_0000_0000_0000_0000");
+        assert_eq!(errors[0].message, "Malformed number '0x1_0000_0000_0000_0000': too large for Int");
+        assert_eq!(lex_errors("1e400")[0].message, "Malformed number '1e400': out of range for Float");
+    }
+
+    #[test]
+    fn placeholders_split_the_string() {
+        assert_eq!(
+            parts("\"Hello {name}, you are {age + 1}\""),
+            [text("Hello "), code("name"), text(", you are "), code("age + 1")]
+        );
+        assert_eq!(parts("\"{a}{b}\""), [code("a"), code("b")]);
+        assert_eq!(tokens("\"{{literal}} }}\""), [Token::String("{literal} }".to_string())]);
+        // Las comillas simples no interpolan.
+        assert_eq!(tokens("'{name}'"), [Token::String("{name}".to_string())]);
+    }
+
+    #[test]
+    fn placeholders_may_nest_braces_and_strings() {
+        assert_eq!(parts("\"{f({a: 1})}!\""), [code("f({a: 1})"), text("!")]);
+        assert_eq!(parts("\"{m[\"key\"]}\""), [code("m[\"key\"]")]);
+        // Un texto con comillas dobles dentro del marcador también interpola.
+        assert_eq!(parts("\"{m[\"}}\"]} {'{'}\""), [code("m[\"}}\"]"), text(" "), code("'{'")]);
+        // Los marcadores anidados guardan su propia posición, así que solo
+        // se compara su forma.
+        let nested = parts("\"{\"inner {x}\"}\"");
+        let [StringPart::Code { tokens, .. }] = nested.as_slice() else {
+            panic!("se esperaba un marcador: {:?}", nested);
+        };
+        let [Token::Interpolated(inner)] = tokens.as_slice() else {
+            panic!("se esperaba un texto interpolado: {:?}", tokens);
+        };
+        assert_eq!(inner[0], text("inner "));
+        assert!(matches!(&inner[1], StringPart::Code { tokens, spans } if tokens == &[Token::Identifier("x".to_string())] && spans == &[span(1, 11, 1)]));
+        assert_eq!(parts("\"{ {\"k\": {\"n\": 2}}[\"k\"] }\""), [code(" {\"k\": {\"n\": 2}}[\"k\"] ")]);
+    }
+
+    #[test]
+    fn placeholder_tokens_keep_their_spans() {
+        let tokens = tokens("define s := \"a {x +\n y}\";");
+        let Token::Interpolated(parts) = &tokens[3] else {
+            panic!("se esperaba un texto interpolado: {:?}", tokens);
+        };
+        let StringPart::Code { spans, .. } = &parts[1] else {
+            panic!("se esperaba código: {:?}", parts);
+        };
+        assert_eq!(spans, &[span(1, 17, 1), span(1, 19, 1), span(2, 2, 1)]);
+    }
+
+    #[test]
+    fn broken_placeholders_are_errors() {
+        let errors = lex_errors("\"a {} b\"");
+        assert_eq!(errors[0].message, "Empty '{}' in string");
+        assert_eq!(errors[0].span, span(1, 4, 2));
+
+        let errors = lex_errors("\"a } b\"");
+        assert_eq!(errors[0].message, "Unmatched '}' in string (write '}}' for a literal brace)");
+        assert_eq!(errors[0].span, span(1, 4, 1));
+
+        let errors = lex_errors("\"a {b\";\nx := 1;");
+        assert_eq!(errors[0].message, "Unterminated '{' in string");
+        assert_eq!(errors[0].span, span(1, 4, 1));
+
+        // Los errores dentro del marcador apuntan a su posición en el archivo.
+        let errors = lex_errors("x := 1;\ns := \"v: {x @ 2}\";");
+        assert_eq!(errors[0].message, "Unexpected character '@'");
+        assert_eq!(errors[0].span, span(2, 13, 1));
+    }
+
+    #[test]
+    fn every_escape_is_decoded() {
+        assert_eq!(string(r#""a\nb\tc\rd\0e\\f\"g\'h""#), "a\nb\tc\rd\0e\\f\"g'h");
+        assert_eq!(string(r#"'a\nb\tc\rd\0e\\f\'g\"h'"#), "a\nb\tc\rd\0e\\f'g\"h");
+        assert_eq!(string(r#""\u{41}\u{e9}\u{1F319}\u{10FFFF}""#), "Aé🌙\u{10FFFF}");
+    }
+
+    #[test]
+    fn invalid_escapes_are_errors() {
+        let unicode = "Invalid unicode escape (expected something like '\\u{1F319}')";
+        let cases = [
+            (r#""ab\qc""#, "Unknown escape sequence '\\q'", 2),
+            (r"'\x41'", "Unknown escape sequence '\\x'", 2),
+            (r#""ab\u41""#, unicode, 3),
+            (r#""ab\u{}""#, unicode, 4),
+            (r#""ab\u{zz}""#, unicode, 3),
+            (r#""ab\u{110000}""#, unicode, 10),
+            (r#""ab\u{D800}""#, unicode, 8),
+            (r#""ab\u{1234567}""#, unicode, 9),
+        ];
+        for (source, message, len) in cases {
+            let errors = lex_errors(source);
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].message, message, "{}", source);
+            let column = source.find('\\').unwrap() + 1;
+            assert_eq!(errors[0].span, span(1, column, len), "{}", source);
+        }
+
+        // Tras un escape inválido, el resto del texto no se lee como código.
+        let errors = lex_errors("define s := \"\\q @ $\";\ndefine t := 2;");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, span(1, 14, 2));
+    }
+
+    #[test]
+    fn raw_strings_keep_their_text() {
+        assert_eq!(string(r#"r"C:\new\{dir}""#), r"C:\new\{dir}");
+        assert_eq!(string(r###"r#"say "hi""#"###), r#"say "hi""#);
+        assert_eq!(string(r###"r##"a"#b"##"###), r##"a"#b"##);
+        assert_eq!(string("r\"line\n  next\""), "line\n  next");
+        assert_eq!(string(r#"r"""#), "");
+        assert_eq!(tokens("r + rust"), [
+            Token::Identifier("r".to_string()),
+            Token::Operator("+".to_string()),
+            Token::Identifier("rust".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn triple_quoted_strings_are_dedented() {
+        let source = "\"\"\"\n    line one\n      indented\n\n    line three\n    \"\"\"";
+        assert_eq!(string(source), "line one\n  indented\n\nline three");
+        // El texto en la línea de apertura cuenta como una línea sin sangría.
+        assert_eq!(string("\"\"\"first\n    second\"\"\""), "first\n    second");
+        assert_eq!(string(r#""""say "hi" """"#), r#"say "hi" "#);
+        assert_eq!(string("\"\"\"\n  a\\tb\\\"\"\"\n  \"\"\""), "a\tb\"\"\"");
+        assert_eq!(
+            parts("\"\"\"\n    Hello {name}\n    \"\"\""),
+            [text("Hello "), code("name")]
+        );
+    }
+
+    #[test]
+    fn mixed_indentation_strips_only_the_shared_prefix() {
+        // Un tabulador y un espacio no son la misma sangría.
+        assert_eq!(string("\"\"\"\n\tone\n    two\n\"\"\""), "\tone\n    two");
+        assert_eq!(string("\"\"\"\n\t  a\n\t    b\n\t\"\"\""), "a\n  b");
+        assert_eq!(string("\"\"\"\n  \t a\n  \t\tb\n\"\"\""), " a\n\tb");
+        // Las líneas en blanco pueden tener menos sangría que el resto.
+        assert_eq!(string("\"\"\"\n    a\n  \n\t\n    b\n    \"\"\""), "a\n\n\t\nb");
+    }
+
+    #[test]
+    fn unterminated_strings_point_at_their_opening_quote() {
+        let cases = [
+            ("define s := \"abc;\ndefine t := 1;", span(1, 13, 1)),
+            ("x := 1;\n  y := 'abc", span(2, 8, 1)),
+            ("s := \"abc\\", span(1, 6, 1)),
+            ("a := 1;\nb := \"\"\"\n  text\n  \"\";", span(2, 6, 1)),
+            ("s := r#\"abc\";", span(1, 6, 1)),
+            ("s := r\"abc", span(1, 6, 1)),
+        ];
+        for (source, at) in cases {
+            let errors = lex_errors(source);
+            assert_eq!(errors.len(), 1, "{:?}", source);
+            assert_eq!(errors[0].message, "Unterminated string", "{:?}", source);
+            assert_eq!(errors[0].span, at, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn unexpected_characters_are_reported_with_their_position() {
+        let cases = [
+            ("x := 1 @ 2;", "Unexpected character '@'", span(1, 8, 1)),
+            ("define $x := 1;", "Unexpected character '$'", span(1, 8, 1)),
+            ("x := 1;\n  y := ~ 2;", "Unexpected '~' (comments start with '~>' or '~<')", span(2, 8, 1)),
+            ("if a ! b {}", "Unexpected '!' (use '!=' or 'not')", span(1, 6, 1)),
+            ("s := 1;\n\n    ¿x", "Unexpected character '¿'", span(3, 5, 1)),
+        ];
+        for (source, message, at) in cases {
+            let errors = lex_errors(source);
+            assert_eq!(errors.len(), 1, "{:?}", source);
+            assert_eq!(errors[0].message, message, "{:?}", source);
+            assert_eq!(errors[0].span, at, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn every_error_is_reported_and_lexing_goes_on() {
+        let errors = lex_errors("a := 1 @ 2;\nb := $;\nc := 0b2 + \"\\q\";\nd := `");
+        let found = errors.iter().map(|e| (e.span.line, e.span.column)).collect::<Vec<_>>();
+        assert_eq!(found, [(1, 8), (2, 6), (3, 6), (3, 13), (4, 6)]);
+        assert_eq!(errors[0].to_string(), "❌ Unexpected character '@' (line 1, column 8)");
+    }
+
+    #[test]
+    fn comments_are_tokens() {
+        assert_eq!(
+            tokens("x ~> note\n~< block\n over lines >~ y"),
+            [
+                Token::Identifier("x".to_string()),
+                Token::Comment(" note".to_string()),
+                Token::Comment(" block\n over lines ".to_string()),
+                Token::Identifier("y".to_string()),
+            ]
+        );
+        assert_eq!(tokens("~< a > b ~ c >~"), [Token::Comment(" a > b ~ c ".to_string())]);
+
+        let errors = lex_errors("x := 1;\n  ~< never closed\nx := 2;");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated '~<' comment (close it with '>~')");
+        assert_eq!(errors[0].span, span(2, 3, 2));
+    }
+
+    #[test]
+    fn colons_and_equals_are_kept_for_the_parser() {
+        assert_eq!(
+            tokens(": :: := = == -> -"),
+            [
+                Token::Symbol(":".to_string()),
+                Token::Symbol("::".to_string()),
+                Token::Operator(":=".to_string()),
+                Token::Operator("=".to_string()),
+                Token::Operator("==".to_string()),
+                Token::Operator("->".to_string()),
+                Token::Operator("-".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn each_token_records_its_span() {
+        let mut lexer = Lexer::new("define x := 1;\n\n~< a\nb >~ x\n  := \"\"\"\n  t\n  \"\"\";");
+        let tokens = lexer.tokenize().expect("el ejemplo debe ser válido");
+        assert_eq!(tokens.len(), lexer.spans.len());
+        let lines = lexer.spans.iter().map(|span| span.line).collect::<Vec<_>>();
+        assert_eq!(lines, [1, 1, 1, 1, 1, 3, 4, 5, 5, 7]);
+        assert_eq!(lexer.spans[..5], [span(1, 1, 6), span(1, 8, 1), span(1, 10, 2), span(1, 13, 1), span(1, 14, 1)]);
+        // Un token que ocupa varias líneas solo marca su primer carácter.
+        assert_eq!(lexer.spans[5], span(3, 1, 1));
+        assert_eq!(lexer.spans[6], span(4, 6, 1));
     }
 }
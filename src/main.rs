@@ -10,41 +10,213 @@ mod ast;
 mod lexer;
 mod parser;
 mod interpreter;
+mod error;
+mod types;
+mod bigint;
+mod clock;
+mod natives;
+mod permissions;
+mod ordered_map;
 mod grimoire;
 mod builtins;
 mod repl;
+#[cfg(test)]
+mod test_support;
 
 use lexer::Lexer;
 use parser::Parser;
 use interpreter::Interpreter;
+use permissions::{Capability, Permissions};
+use clock::Clock;
 use crate::grimoire::Grimoire;
 
+/// Stack used by each nested Lunaria call, with a margin over what was
+/// measured: one call nests several large `eval` frames, and a debug build
+/// uses several times more stack than a release build.
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) { 96 * 1024 } else { 16 * 1024 };
+/// Stack for everything besides nested calls: lexing, parsing, natives.
+const STACK_BASE: usize = 64 * 1024 * 1024;
+/// Largest stack reserved for the interpreter thread.
+const MAX_STACK_SIZE: usize = 1024 * 1024 * 1024;
+/// Deepest `--max-call-depth` whose calls fit in `MAX_STACK_SIZE`; deeper
+/// values would turn a `StackOverflowError` into a crash.
+const MAX_CALL_DEPTH_LIMIT: usize = (MAX_STACK_SIZE - STACK_BASE) / STACK_PER_CALL;
+
+/// Stack size for the thread that runs a script, so that `max_call_depth`
+/// nested calls fit and the limit is reported as a `StackOverflowError`.
+fn stack_size_for(max_call_depth: usize) -> usize {
+    STACK_BASE + max_call_depth.min(MAX_CALL_DEPTH_LIMIT) * STACK_PER_CALL
+}
+
+/// Stack kept free below the interpreter's `stack_limit`, for whatever runs
+/// between two of its checks (a native, a deep `eval` frame).
+const STACK_RESERVE: usize = 4 * 1024 * 1024;
+
+/// Sets the call depth limit and the stack the interpreter may use on a
+/// thread of `stack_size_for(max_call_depth)`.
+fn limit_depth(interpreter: &mut Interpreter, max_call_depth: usize) {
+    interpreter.max_call_depth = max_call_depth;
+    interpreter.stack_limit = stack_size_for(max_call_depth) - STACK_RESERVE;
+}
+
+/// Interpreter settings taken from the command line.
+struct Options {
+    max_call_depth: usize,
+    seed: Option<u64>,
+    /// `--fake-time=MS` freezes the `time` module's clock at that many
+    /// milliseconds since the Unix epoch.
+    fake_time: Option<i64>,
+    /// `--verbose` reports declarations and assignments on stderr.
+    verbose: bool,
+    /// Everything after `--`, passed to `fn main(args::List<Text>)`.
+    script_args: Vec<String>,
+    /// Granted with `--allow-read=a,b`, `--allow-run` (no value: anything)
+    /// or `--allow-all`.
+    permissions: Permissions,
+}
+
+impl Options {
+    /// Removes the `--flag=value` and `--allow-*` options from `args`,
+    /// leaving the program name and the script path.
+    fn parse(args: &mut Vec<String>) -> Self {
+        // Todo lo que sigue a `--` pertenece al script, no al intérprete.
+        let script_args = match args.iter().position(|a| a == "--") {
+            Some(split) => {
+                let rest = args.split_off(split + 1);
+                args.pop();
+                rest
+            }
+            None => Vec::new(),
+        };
+        let mut options = Options {
+            max_call_depth: interpreter::DEFAULT_MAX_CALL_DEPTH,
+            seed: None,
+            fake_time: None,
+            verbose: false,
+            script_args,
+            permissions: Permissions::default(),
+        };
+        args.retain(|arg| {
+            if !arg.starts_with("--") {
+                return true;
+            }
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if flag == "--allow-all" {
+                options.permissions = Permissions::all();
+                return false;
+            }
+            if let Some(cap) = Capability::from_flag(flag) {
+                match value {
+                    Some(targets) => {
+                        for target in targets.split(',').filter(|t| !t.is_empty()) {
+                            options.permissions.grant(cap, Some(target));
+                        }
+                    }
+                    None => options.permissions.grant(cap, None),
+                }
+                return false;
+            }
+            let value = value.unwrap_or("");
+            let valid = match flag {
+                "--max-call-depth" => match value.parse::<usize>() {
+                    Ok(n) if n > MAX_CALL_DEPTH_LIMIT => {
+                        eprintln!("⚠️ --max-call-depth no puede superar {}; se usará ese valor", MAX_CALL_DEPTH_LIMIT);
+                        options.max_call_depth = MAX_CALL_DEPTH_LIMIT;
+                        true
+                    }
+                    Ok(n) => {
+                        options.max_call_depth = n;
+                        true
+                    }
+                    Err(_) => false,
+                },
+                "--seed" => value.parse().map(|n| options.seed = Some(n)).is_ok(),
+                "--fake-time" => value.parse().map(|n| options.fake_time = Some(n)).is_ok(),
+                "--verbose" => {
+                    options.verbose = true;
+                    true
+                }
+                _ => {
+                    eprintln!("⚠️ Opción desconocida: '{}'", flag);
+                    true
+                }
+            };
+            if !valid {
+                eprintln!("⚠️ Valor inválido para {}: '{}'", flag, value);
+            }
+            false
+        });
+        options
+    }
+
+    fn apply(self, interpreter: &mut Interpreter) {
+        limit_depth(interpreter, self.max_call_depth);
+        interpreter.verbose = self.verbose;
+        interpreter.script_args = self.script_args;
+        interpreter.permissions = self.permissions;
+        if let Some(seed) = self.seed {
+            interpreter.seed_random(seed);
+        }
+        if let Some(now_ms) = self.fake_time {
+            interpreter.clock = Clock::fake(now_ms);
+        }
+    }
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let options = Options::parse(&mut args);
+
+    let runner = std::thread::Builder::new()
+        .stack_size(stack_size_for(options.max_call_depth))
+        .spawn(move || run_file(args, options));
+    let code = match runner {
+        Ok(runner) => runner.join().unwrap_or(101),
+        Err(e) => {
+            eprintln!("❌ No se pudo iniciar el hilo del intérprete: {}", e);
+            1
+        }
+    };
+    std::process::exit(code);
+}
+
+/// Runs the script named in `args` (or the REPL) and returns the exit code.
+fn run_file(args: Vec<String>, options: Options) -> i32 {
+    let mut exit_code = 0;
     let target_file = args.get(1).cloned().unwrap_or("main.lna".to_string());
 
     match fs::read_to_string(&target_file) {
         Ok(code) => {
             if code.trim().is_empty() {
-                println!("🌑 El grimorio está en blanco... nada que conjurar.");
+                eprintln!("🌑 El grimorio está en blanco... nada que conjurar.");
             } else {
                 let mut lexer = Lexer::new(&code);
-                let tokens = lexer.tokenize();
+                let parsed = lexer
+                    .tokenize()
+                    .map_err(|errors| errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))
+                    .and_then(|tokens| Parser::new(tokens).with_spans(lexer.spans.clone()).parse());
 
-                let mut parser = Parser::new(tokens);
-                match parser.parse() {
+                match parsed {
                     Ok(ast) => {
                         let mut interpreter = Interpreter::new();
-                        interpreter.interpret(ast);
+                        options.apply(&mut interpreter);
+                        exit_code = interpreter.interpret(ast);
                     }
                     Err(e) => {
-                        println!("❌ Error al interpretar '{}': {}", target_file, e);
+                        eprintln!("❌ Error al interpretar '{}': {}", target_file, e);
+                        exit_code = 1;
                     }
                 }
             }
         }
         Err(_) => {
-            println!("⚠️ No se pudo encontrar '{}'.", target_file);
+            eprintln!("⚠️ No se pudo encontrar '{}'.", target_file);
+            if args.get(1).is_some() {
+                exit_code = 1;
+            }
         }
     }
 
@@ -55,4 +227,70 @@ fn main() {
         // Start the repl environment
         repl::start_repl(Grimoire::new());
     }
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorKind, RuntimeError};
+    use crate::test_support::run_on;
+
+    /// Runs `source` the way `main` does: on its own thread, with the stack
+    /// sized for `max_call_depth`.
+    fn run_with_depth(source: String, max_call_depth: usize) -> Result<i32, RuntimeError> {
+        std::thread::Builder::new()
+            .stack_size(stack_size_for(max_call_depth))
+            .spawn(move || {
+                let mut interpreter = Interpreter::new();
+                limit_depth(&mut interpreter, max_call_depth);
+                run_on(interpreter, &source)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn interpreter_options_are_taken_out_of_the_arguments() {
+        let mut args = ["lunaria", "--verbose", "--seed=7", "script.lna", "--", "--verbose"].map(String::from).to_vec();
+        let options = Options::parse(&mut args);
+        assert_eq!(args, ["lunaria", "script.lna"]);
+        assert!(options.verbose);
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.script_args, ["--verbose"]);
+        assert!(!Options::parse(&mut vec!["lunaria".to_string()]).verbose);
+    }
+
+    #[test]
+    fn the_deepest_allowed_limit_is_a_stack_overflow_error() {
+        let source = "fn down(n::int) -> int {\n    if n < 0 { return 0 }\n    return 1 + down(n + 1)\n}\ndown(0)\n";
+        let err = run_with_depth(source.to_string(), MAX_CALL_DEPTH_LIMIT).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::StackOverflow);
+    }
+
+    #[test]
+    fn the_deepest_allowed_limit_can_be_caught_inside_nested_blocks() {
+        let source = "fn down(n::int) -> int {\n    while true {\n        if n >= 0 {\n            try { return down(n + 1) } catch e {\n                if e[\"kind\"] == \"StackOverflowError\" { return 7 }\n                return 0\n            }\n        }\n    }\n    return 0\n}\nfn main() -> int { return down(0) }\n";
+        assert_eq!(run_with_depth(source.to_string(), MAX_CALL_DEPTH_LIMIT).unwrap(), 7);
+    }
+
+    /// A recursive function whose call sits inside `ops` nested additions,
+    /// so each frame needs much more stack than a bare call.
+    fn nested_recursion(ops: usize, main_body: &str) -> String {
+        let call = (0..ops).fold("down(n + 1)".to_string(), |inner, _| format!("(1 + {})", inner));
+        format!("fn down(n::int) -> int {{\n    return {}\n}}\nfn main() -> int {{ {} }}\n", call, main_body)
+    }
+
+    #[test]
+    fn nested_expressions_in_every_frame_are_a_stack_overflow_error() {
+        for ops in [8, 32] {
+            for depth in [interpreter::DEFAULT_MAX_CALL_DEPTH, MAX_CALL_DEPTH_LIMIT] {
+                let err = run_with_depth(nested_recursion(ops, "return down(0)"), depth).unwrap_err();
+                assert_eq!(err.kind, ErrorKind::StackOverflow, "{} operaciones, profundidad {}", ops, depth);
+            }
+        }
+        let caught = "try { return down(0) } catch e { if e[\"kind\"] == \"StackOverflowError\" { return 7 } }\n    return 0";
+        assert_eq!(run_with_depth(nested_recursion(32, caught), MAX_CALL_DEPTH_LIMIT).unwrap(), 7);
+    }
 }
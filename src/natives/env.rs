@@ -0,0 +1,25 @@
+//! The `env` module: the process environment, behind the `Env`
+//! capability (`--allow-env=HOME` or `--allow-env`).
+
+use super::{expect_args_between, expect_text};
+use crate::ast::Value;
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::permissions::Capability;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("env.get", get);
+}
+
+/// `env.get(name)` is the variable's value, or `void` when it is not set
+/// (or not valid Unicode); `env.get(name, default)` returns `default`
+/// instead.
+fn get(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("env.get", &args, 1, 2)?;
+    let name = expect_text("env.get", &args, 0)?;
+    interpreter.permissions.check("env.get", Capability::Env, &name)?;
+    match std::env::var(&name) {
+        Ok(value) => Ok(Value::String(value)),
+        Err(_) => Ok(args.get(1).cloned().unwrap_or(Value::Void)),
+    }
+}
@@ -0,0 +1,158 @@
+//! The `fs` module. Paths are `Text` and relative to the working
+//! directory; contents are read and written as UTF-8 text. Any failure
+//! reported by the operating system becomes an `IOError` naming the path.
+//! Reading (`read`, `exists`, `list_dir`, `metadata`) needs the `Read`
+//! capability for the path and changing anything needs `Write`; see
+//! `crate::permissions`.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::{arg_type_error, expect_args, expect_args_between, expect_text};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::Interpreter;
+use crate::ordered_map::OrderedMap;
+use crate::permissions::Capability;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("fs.read", |interpreter, args| {
+        expect_args("fs.read", &args, 1)?;
+        let path = expect_text("fs.read", &args, 0)?;
+        interpreter.permissions.check_path("fs.read", Capability::Read, &path)?;
+        let content = fs::read_to_string(&path).map_err(|e| io_error("fs.read", &path, e))?;
+        Ok(Value::String(content))
+    });
+    interpreter.define_native("fs.write", |interpreter, args| write(interpreter, "fs.write", args));
+    // Nombre heredado del intérprete original; lo usan `build.lna` y `lunaria.lna`.
+    interpreter.define_native("fs.out", |interpreter, args| write(interpreter, "fs.out", args));
+    interpreter.define_native("fs.append", append);
+    interpreter.define_native("fs.exists", |interpreter, args| {
+        expect_args("fs.exists", &args, 1)?;
+        let path = expect_text("fs.exists", &args, 0)?;
+        interpreter.permissions.check_path("fs.exists", Capability::Read, &path)?;
+        Ok(Value::Boolean(Path::new(&path).exists()))
+    });
+    interpreter.define_native("fs.remove", remove);
+    interpreter.define_native("fs.mkdir", |interpreter, args| {
+        expect_args("fs.mkdir", &args, 1)?;
+        let path = expect_text("fs.mkdir", &args, 0)?;
+        interpreter.permissions.check_path("fs.mkdir", Capability::Write, &path)?;
+        fs::create_dir_all(&path).map_err(|e| io_error("fs.mkdir", &path, e))?;
+        Ok(Value::Void)
+    });
+    interpreter.define_native("fs.list_dir", list_dir);
+    interpreter.define_native("fs.copy", |interpreter, args| {
+        let (from, to) = path_pair("fs.copy", &args)?;
+        interpreter.permissions.check_path("fs.copy", Capability::Read, &from)?;
+        interpreter.permissions.check_path("fs.copy", Capability::Write, &to)?;
+        fs::copy(&from, &to).map_err(|e| io_error("fs.copy", &from, e))?;
+        Ok(Value::Void)
+    });
+    interpreter.define_native("fs.rename", |interpreter, args| {
+        let (from, to) = path_pair("fs.rename", &args)?;
+        interpreter.permissions.check_path("fs.rename", Capability::Write, &from)?;
+        interpreter.permissions.check_path("fs.rename", Capability::Write, &to)?;
+        fs::rename(&from, &to).map_err(|e| io_error("fs.rename", &from, e))?;
+        Ok(Value::Void)
+    });
+    interpreter.define_native("fs.metadata", metadata);
+}
+
+fn io_error(name: &str, path: &str, error: std::io::Error) -> RuntimeError {
+    RuntimeError::new(ErrorKind::Io, format!("'{}': '{}': {}", name, path, error))
+}
+
+fn path_pair(name: &str, args: &[Value]) -> Result<(String, String), RuntimeError> {
+    expect_args(name, args, 2)?;
+    Ok((expect_text(name, args, 0)?, expect_text(name, args, 1)?))
+}
+
+/// `fs.write(path, content)` replaces the file, creating it and any
+/// missing parent directories first.
+fn write(interpreter: &mut Interpreter, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (path, content) = path_pair(name, &args)?;
+    interpreter.permissions.check_path(name, Capability::Write, &path)?;
+    if let Some(parent) = Path::new(&path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| io_error(name, &parent.to_string_lossy(), e))?;
+    }
+    fs::write(&path, content).map_err(|e| io_error(name, &path, e))?;
+    Ok(Value::Void)
+}
+
+/// `fs.append(path, content)` adds to the end of the file, creating it if
+/// it does not exist.
+fn append(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (path, content) = path_pair("fs.append", &args)?;
+    interpreter.permissions.check_path("fs.append", Capability::Write, &path)?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| io_error("fs.append", &path, e))?;
+    Ok(Value::Void)
+}
+
+/// `fs.remove(path)` deletes a file or an empty directory;
+/// `fs.remove(path, true)` also deletes a directory with its contents.
+fn remove(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("fs.remove", &args, 1, 2)?;
+    let path = expect_text("fs.remove", &args, 0)?;
+    interpreter.permissions.check_path("fs.remove", Capability::Write, &path)?;
+    let recursive = match args.get(1) {
+        None => false,
+        Some(Value::Boolean(b)) => *b,
+        Some(other) => return Err(arg_type_error("fs.remove", 1, "'Bool'", other)),
+    };
+    let target = Path::new(&path);
+    let result = match fs::symlink_metadata(target) {
+        Ok(meta) if meta.is_dir() && recursive => fs::remove_dir_all(target),
+        Ok(meta) if meta.is_dir() => fs::remove_dir(target),
+        Ok(_) => fs::remove_file(target),
+        Err(e) => Err(e),
+    };
+    result.map_err(|e| io_error("fs.remove", &path, e))?;
+    Ok(Value::Void)
+}
+
+/// The names of the entries in a directory, sorted so the result does not
+/// depend on the file system.
+fn list_dir(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("fs.list_dir", &args, 1)?;
+    let path = expect_text("fs.list_dir", &args, 0)?;
+    interpreter.permissions.check_path("fs.list_dir", Capability::Read, &path)?;
+    let mut names = fs::read_dir(&path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| io_error("fs.list_dir", &path, e))?;
+    names.sort();
+    Ok(Value::List(names.into_iter().map(Value::String).collect()))
+}
+
+/// A map with `size` (bytes), `is_file`, `is_dir`, `readonly` and
+/// `modified` (seconds since the Unix epoch, or `void` if unknown).
+fn metadata(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("fs.metadata", &args, 1)?;
+    let path = expect_text("fs.metadata", &args, 0)?;
+    interpreter.permissions.check_path("fs.metadata", Capability::Read, &path)?;
+    let meta = fs::metadata(&path).map_err(|e| io_error("fs.metadata", &path, e))?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(Value::Void, |d| Value::Integer(d.as_secs() as i64));
+
+    let mut map = OrderedMap::new();
+    map.insert("size".to_string(), Value::Integer(meta.len() as i64));
+    map.insert("is_file".to_string(), Value::Boolean(meta.is_file()));
+    map.insert("is_dir".to_string(), Value::Boolean(meta.is_dir()));
+    map.insert("readonly".to_string(), Value::Boolean(meta.permissions().readonly()));
+    map.insert("modified".to_string(), modified);
+    Ok(Value::Map(map))
+}
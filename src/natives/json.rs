@@ -0,0 +1,554 @@
+//! The `json` module. Values map to JSON as follows:
+//!
+//! - `Map` ↔ object (key order is kept both ways), `List` ↔ array,
+//!   `Text` ↔ string, `Bool` ↔ `true`/`false`, `Void` ↔ `null`;
+//! - numbers without a fraction or exponent parse as `Int`, or `BigInt`
+//!   when too large; any other number parses as `Float`. `Float`s that are
+//!   NaN or infinite have no JSON form and are a `ValueError`;
+//! - functions cannot be stringified (`TypeError`).
+//!
+//! Malformed input is a `ValueError` pointing at the line and column, and
+//! so is nesting arrays and objects more than `MAX_DEPTH` levels deep.
+
+use super::{arg_type_error, expect_args, expect_args_between, expect_text};
+use crate::ast::Value;
+use crate::bigint::BigInt;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::Interpreter;
+use crate::ordered_map::OrderedMap;
+use crate::types::Type;
+
+/// Deepest nesting of arrays and objects `json.parse` accepts, as in
+/// `serde_json`; the parser recurses once per level.
+const MAX_DEPTH: usize = 128;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("json.parse", |_, args| {
+        expect_args("json.parse", &args, 1)?;
+        let text = expect_text("json.parse", &args, 0)?;
+        JsonParser::new(&text).parse_document()
+    });
+    interpreter.define_native("json.stringify", |_, args| stringify(args));
+}
+
+/// `json.stringify(value)` is compact; `json.stringify(value, true)`
+/// indents nested values by two spaces.
+fn stringify(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("json.stringify", &args, 1, 2)?;
+    let pretty = match args.get(1) {
+        None => false,
+        Some(Value::Boolean(b)) => *b,
+        Some(other) => return Err(arg_type_error("json.stringify", 1, "'Bool'", other)),
+    };
+    let mut out = String::new();
+    write_value(&mut out, &args[0], pretty, 0)?;
+    Ok(Value::String(out))
+}
+
+fn write_value(out: &mut String, value: &Value, pretty: bool, depth: usize) -> Result<(), RuntimeError> {
+    match value {
+        Value::Void => out.push_str("null"),
+        Value::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Integer(n) => out.push_str(&n.to_string()),
+        Value::BigInt(n) => out.push_str(&n.to_string()),
+        Value::Float(f) if f.is_finite() => out.push_str(&format!("{:?}", f)),
+        Value::Float(f) => {
+            return Err(RuntimeError::new(
+                ErrorKind::Value,
+                format!("'json.stringify': {:?} no se puede representar en JSON", f),
+            ));
+        }
+        Value::String(text) => write_string(out, text),
+        Value::List(items) => {
+            write_container(out, '[', ']', items.iter(), pretty, depth, |out, item, depth| {
+                write_value(out, item, pretty, depth)
+            })?;
+        }
+        Value::Map(map) => {
+            write_container(out, '{', '}', map.iter(), pretty, depth, |out, (key, item), depth| {
+                write_string(out, key);
+                out.push_str(if pretty { ": " } else { ":" });
+                write_value(out, item, pretty, depth)
+            })?;
+        }
+        Value::Function(_) => {
+            return Err(RuntimeError::type_error(format!(
+                "'json.stringify': un valor de tipo '{}' no se puede convertir a JSON",
+                Type::of(value)
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Writes `items` between `open` and `close`, one per line when `pretty`.
+fn write_container<T>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: impl Iterator<Item = T>,
+    pretty: bool,
+    depth: usize,
+    mut write_item: impl FnMut(&mut String, T, usize) -> Result<(), RuntimeError>,
+) -> Result<(), RuntimeError> {
+    out.push(open);
+    let mut empty = true;
+    for (i, item) in items.enumerate() {
+        empty = false;
+        if i > 0 {
+            out.push(',');
+        }
+        if pretty {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth + 1));
+        }
+        write_item(out, item, depth + 1)?;
+    }
+    if pretty && !empty {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+    }
+    out.push(close);
+    Ok(())
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0C}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// A recursive-descent JSON reader that tracks line and column (both
+/// 1-based, columns counted in characters) for its error messages.
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    /// Arrays and objects currently open.
+    depth: usize,
+}
+
+impl JsonParser {
+    fn new(text: &str) -> Self {
+        JsonParser { chars: text.chars().collect(), pos: 0, line: 1, column: 1, depth: 0 }
+    }
+
+    fn error(&self, message: impl Into<String>) -> RuntimeError {
+        RuntimeError::new(
+            ErrorKind::Value,
+            format!("'json.parse': {} (línea {}, columna {})", message.into(), self.line, self.column),
+        )
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.advance();
+        }
+    }
+
+    fn unexpected(&self) -> RuntimeError {
+        match self.peek() {
+            Some(c) => self.error(format!("carácter inesperado '{}'", c)),
+            None => self.error("fin del texto inesperado"),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), RuntimeError> {
+        if self.peek() == Some(expected) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(format!("se esperaba '{}'", expected)))
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Value, RuntimeError> {
+        self.skip_whitespace();
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.peek().is_some() {
+            return Err(self.error("contenido después del valor JSON"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, RuntimeError> {
+        match self.peek() {
+            Some(open @ ('{' | '[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error(format!("más de {} niveles de anidamiento", MAX_DEPTH)));
+                }
+                self.depth += 1;
+                let value = if open == '{' { self.parse_object() } else { self.parse_array() };
+                self.depth -= 1;
+                value
+            }
+            Some('"') => Ok(Value::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", Value::Boolean(true)),
+            Some('f') => self.parse_literal("false", Value::Boolean(false)),
+            Some('n') => self.parse_literal("null", Value::Void),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_literal(&mut self, word: &str, value: Value) -> Result<Value, RuntimeError> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.unexpected());
+            }
+            self.advance();
+        }
+        Ok(value)
+    }
+
+    /// Objects keep their key order; a repeated key keeps its first
+    /// position and its last value.
+    fn parse_object(&mut self) -> Result<Value, RuntimeError> {
+        self.expect('{')?;
+        let mut map = OrderedMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Value::Map(map));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("se esperaba una clave entre comillas"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            map.insert(key, value);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.advance();
+                }
+                Some('}') => {
+                    self.advance();
+                    return Ok(Value::Map(map));
+                }
+                _ => return Err(self.error("se esperaba ',' o '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, RuntimeError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(Value::List(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.advance();
+                }
+                Some(']') => {
+                    self.advance();
+                    return Ok(Value::List(items));
+                }
+                _ => return Err(self.error("se esperaba ',' o ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, RuntimeError> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("texto sin cerrar")),
+                Some('"') => {
+                    self.advance();
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.advance();
+                    text.push(self.parse_escape()?);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("carácter de control sin escapar dentro del texto"));
+                }
+                Some(c) => {
+                    self.advance();
+                    text.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, RuntimeError> {
+        let escaped = match self.peek() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{08}',
+            Some('f') => '\u{0C}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                self.advance();
+                return self.parse_unicode_escape();
+            }
+            Some(c) => return Err(self.error(format!("secuencia de escape desconocida '\\{}'", c))),
+            None => return Err(self.error("texto sin cerrar")),
+        };
+        self.advance();
+        Ok(escaped)
+    }
+
+    /// The part of `\uXXXX` after the `u`, combining UTF-16 surrogate
+    /// pairs written as two escapes.
+    fn parse_unicode_escape(&mut self) -> Result<char, RuntimeError> {
+        let high = self.parse_hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error(format!("'\\u{:04x}' no es un carácter válido", high)));
+        }
+        if self.peek() != Some('\\') || self.chars.get(self.pos + 1) != Some(&'u') {
+            return Err(self.error("falta la segunda mitad del par sustituto"));
+        }
+        self.advance();
+        self.advance();
+        let low = self.parse_hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error(format!("'\\u{:04x}' no es la segunda mitad de un par sustituto", low)));
+        }
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        Ok(char::from_u32(code).expect("un par sustituto siempre forma un carácter válido"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, RuntimeError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("'\\u' necesita cuatro dígitos hexadecimales"))?;
+            self.advance();
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn take_digits(&mut self, number: &mut String) -> usize {
+        let mut count = 0;
+        while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+            self.advance();
+            number.push(c);
+            count += 1;
+        }
+        count
+    }
+
+    fn parse_number(&mut self) -> Result<Value, RuntimeError> {
+        let mut number = String::new();
+        if self.peek() == Some('-') {
+            self.advance();
+            number.push('-');
+        }
+        if self.peek() == Some('0') {
+            self.advance();
+            number.push('0');
+            if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                return Err(self.error("los números no pueden tener ceros a la izquierda"));
+            }
+        } else if self.take_digits(&mut number) == 0 {
+            return Err(self.error("se esperaba un dígito"));
+        }
+
+        let mut is_float = false;
+        if self.peek() == Some('.') {
+            self.advance();
+            number.push('.');
+            if self.take_digits(&mut number) == 0 {
+                return Err(self.error("se esperaba un dígito después del punto decimal"));
+            }
+            is_float = true;
+        }
+        if let Some(e @ ('e' | 'E')) = self.peek() {
+            self.advance();
+            number.push(e);
+            if let Some(sign @ ('+' | '-')) = self.peek() {
+                self.advance();
+                number.push(sign);
+            }
+            if self.take_digits(&mut number) == 0 {
+                return Err(self.error("se esperaba un dígito en el exponente"));
+            }
+            is_float = true;
+        }
+
+        if !is_float {
+            if let Ok(n) = number.parse::<i64>() {
+                return Ok(Value::Integer(n));
+            }
+            if let Some(n) = BigInt::parse(&number) {
+                return Ok(Value::BigInt(n));
+            }
+        }
+        match number.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(Value::Float(f)),
+            _ => Err(self.error(format!("el número {} no cabe en 'Float'", number))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::text;
+
+    fn parse(text: &str) -> Result<Value, RuntimeError> {
+        JsonParser::new(text).parse_document()
+    }
+
+    fn compact(value: &Value) -> String {
+        match stringify(vec![value.clone()]).unwrap() {
+            Value::String(s) => s,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /// The message of the error for `text`, which must not parse.
+    fn error(text: &str) -> String {
+        let err = parse(text).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Value, "{:?}", text);
+        err.message
+    }
+
+    #[test]
+    fn escape_sequences_are_decoded_and_encoded() {
+        let parsed = parse(r#""a\"b\\c\/d\b\f\n\r\té🌙""#).unwrap();
+        assert_eq!(parsed, text("a\"b\\c/d\u{08}\u{0C}\n\r\té🌙"));
+        assert_eq!(compact(&parsed), r#""a\"b\\c/d\b\f\n\r\té🌙""#);
+        assert_eq!(compact(&text("\u{01}\u{1f}")), r#""\u0001\u001f""#);
+        for bad in [r#""\x""#, r#""\u12""#, r#""\ud83c""#, r#""\ud83cA""#, r#""\udc00""#, "\"a\nb\"", "\"abc"] {
+            error(bad);
+        }
+    }
+
+    #[test]
+    fn nested_structures_keep_their_shape_and_order() {
+        let source = r#"{"z": [1, {"b": null}], "a": {"d": [true, false, []]}, "m": {}}"#;
+        let parsed = parse(source).unwrap();
+        let Value::Map(map) = &parsed else { panic!("{:?}", parsed) };
+        assert_eq!(map.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(), ["z", "a", "m"]);
+        let mut inner = OrderedMap::new();
+        inner.insert("b".to_string(), Value::Void);
+        assert_eq!(map.get("z"), Some(&Value::List(vec![Value::Integer(1), Value::Map(inner)])));
+        assert_eq!(compact(&parsed), r#"{"z":[1,{"b":null}],"a":{"d":[true,false,[]]},"m":{}}"#);
+
+        let pretty = stringify(vec![parse(r#"{"a": [1, {}], "b": []}"#).unwrap(), Value::Boolean(true)]).unwrap();
+        assert_eq!(pretty, text("{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": []\n}"));
+
+        assert_eq!(compact(&parse(r#"{"x": 1, "y": 2, "x": 3}"#).unwrap()), r#"{"x":3,"y":2}"#);
+    }
+
+    #[test]
+    fn numbers_pick_the_narrowest_type() {
+        assert_eq!(parse("0").unwrap(), Value::Integer(0));
+        assert_eq!(parse("-0").unwrap(), Value::Integer(0));
+        assert_eq!(parse("9223372036854775807").unwrap(), Value::Integer(i64::MAX));
+        assert_eq!(parse("-9223372036854775808").unwrap(), Value::Integer(i64::MIN));
+        for big in ["9223372036854775808", "-9223372036854775809", "123456789012345678901234567890"] {
+            assert_eq!(parse(big).unwrap(), Value::BigInt(BigInt::parse(big).unwrap()));
+        }
+        assert_eq!(parse("1.5e3").unwrap(), Value::Float(1500.0));
+        assert_eq!(parse("1E-2").unwrap(), Value::Float(0.01));
+        assert_eq!(parse("-0.0").unwrap(), Value::Float(-0.0));
+        assert_eq!(parse("1e2").unwrap(), Value::Float(100.0));
+        for bad in ["01", "-01", "1.", ".5", "-", "+1", "1e", "1e+", "1.e5", "1e400", "-1e400", "0x10"] {
+            error(bad);
+        }
+    }
+
+    #[test]
+    fn floats_keep_a_fraction_and_non_finite_floats_are_errors() {
+        assert_eq!(compact(&Value::Float(1.0)), "1.0");
+        assert_eq!(compact(&Value::Float(-2.5e-8)), "-2.5e-8");
+        assert_eq!(parse(&compact(&Value::Float(0.1))).unwrap(), Value::Float(0.1));
+        for f in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let err = stringify(vec![Value::List(vec![Value::Float(f)])]).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Value);
+        }
+    }
+
+    #[test]
+    fn trailing_garbage_is_rejected() {
+        assert_eq!(parse(" [1] \n\t").unwrap(), Value::List(vec![Value::Integer(1)]));
+        assert!(error("[1] x").contains("contenido después del valor JSON"));
+        error("{} {}");
+        error("1 2");
+        error("truex");
+        error("nul");
+        error("[1,]");
+        error(r#"{"a": 1,}"#);
+        error("");
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |levels: usize| format!("{}{}", "[".repeat(levels), "]".repeat(levels));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        let objects = |levels: usize| format!("{}1{}", "{\"a\": ".repeat(levels), "}".repeat(levels));
+        assert!(parse(&objects(MAX_DEPTH)).is_ok());
+        assert!(error(&objects(MAX_DEPTH + 1)).contains("niveles de anidamiento"));
+        let message = error(&nested(MAX_DEPTH + 1));
+        assert!(message.ends_with("más de 128 niveles de anidamiento (línea 1, columna 129)"), "{}", message);
+        let message = error(&format!("{{\"a\":\n{}", "[".repeat(100_000)));
+        assert!(message.ends_with("(línea 2, columna 128)"), "{}", message);
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_column() {
+        let message = error("{\n  \"a\": 1,\n  \"b\" 2\n}");
+        assert!(message.ends_with("se esperaba ':' (línea 3, columna 7)"), "{}", message);
+        let message = error("[1,\n2,\n");
+        assert!(message.ends_with("fin del texto inesperado (línea 3, columna 1)"), "{}", message);
+        // Las columnas cuentan caracteres, no bytes.
+        let message = error("\"ñ🌙\" x");
+        assert!(message.ends_with("(línea 1, columna 6)"), "{}", message);
+        let message = error("[1, 2\r\n  3]");
+        assert!(message.ends_with("se esperaba ',' o ']' (línea 2, columna 3)"), "{}", message);
+    }
+}
@@ -0,0 +1,456 @@
+//! The `lists` module. Lists are values, as everywhere else in Lunaria:
+//! no native modifies its argument. `push`, `pop`, `insert`, `remove`,
+//! `reverse` and `sort` return a new list, so updating a variable reads
+//! `xs := lists.push(xs, 4)`. To take the last element, read `xs[-1]`
+//! before `xs := lists.pop(xs)`. `range` refuses to build a list longer
+//! than `MAX_RANGE_ITEMS`.
+
+use super::{expect_args, expect_args_between, expect_function, expect_int, expect_list, is_number, value_error};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::{binary_op, values_equal, Interpreter};
+use crate::types::Type;
+
+/// Largest list that `range` will build.
+const MAX_RANGE_ITEMS: usize = 16 * 1024 * 1024;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("lists.len", |_, args| {
+        expect_args("lists.len", &args, 1)?;
+        Ok(Value::Integer(expect_list("lists.len", &args, 0)?.len() as i64))
+    });
+    interpreter.define_native("lists.push", |_, args| push(args));
+    interpreter.define_native("lists.pop", |_, args| pop(args));
+    interpreter.define_native("lists.insert", |_, args| insert(args));
+    interpreter.define_native("lists.remove", |_, args| remove(args));
+    interpreter.define_native("lists.concat", |_, args| concat(args));
+    interpreter.define_native("lists.reverse", |_, args| {
+        expect_args("lists.reverse", &args, 1)?;
+        let mut items = expect_list("lists.reverse", &args, 0)?;
+        items.reverse();
+        Ok(Value::List(items))
+    });
+    interpreter.define_native("lists.sort", sort);
+    interpreter.define_native("lists.contains", |_, args| {
+        expect_args("lists.contains", &args, 2)?;
+        let items = expect_list("lists.contains", &args, 0)?;
+        Ok(Value::Boolean(items.iter().any(|item| values_equal(item, &args[1]))))
+    });
+    interpreter.define_native("lists.index_of", |_, args| {
+        expect_args("lists.index_of", &args, 2)?;
+        let items = expect_list("lists.index_of", &args, 0)?;
+        let index = items.iter().position(|item| values_equal(item, &args[1]));
+        Ok(Value::Integer(index.map_or(-1, |i| i as i64)))
+    });
+    interpreter.define_native("lists.range", |_, args| range(args));
+    interpreter.define_native("lists.zip", |_, args| zip(args));
+    interpreter.define_native("lists.enumerate", |_, args| {
+        expect_args("lists.enumerate", &args, 1)?;
+        let items = expect_list("lists.enumerate", &args, 0)?;
+        let pairs = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| Value::List(vec![Value::Integer(i as i64), item]))
+            .collect();
+        Ok(Value::List(pairs))
+    });
+    interpreter.define_native("lists.map", map);
+    interpreter.define_native("lists.filter", filter);
+    interpreter.define_native("lists.reduce", reduce);
+    interpreter.define_native("lists.any", |interpreter, args| quantifier(interpreter, "lists.any", args));
+    interpreter.define_native("lists.all", |interpreter, args| quantifier(interpreter, "lists.all", args));
+    interpreter.define_native("lists.sum", |_, args| sum(args));
+}
+
+fn index_error(name: &str, index: i64, len: usize) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Index,
+        format!("'{}': índice {} fuera de rango para una lista de longitud {}", name, index, len),
+    )
+}
+
+/// Resolves a possibly negative index; `allow_end` also accepts `len`
+/// (the position after the last element, for `insert`).
+fn position(name: &str, index: i64, len: usize, allow_end: bool) -> Result<usize, RuntimeError> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    let limit = if allow_end { len as i64 + 1 } else { len as i64 };
+    if (0..limit).contains(&resolved) {
+        Ok(resolved as usize)
+    } else {
+        Err(index_error(name, index, len))
+    }
+}
+
+fn push(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.push", &args, 2)?;
+    let mut items = expect_list("lists.push", &args, 0)?;
+    items.push(args[1].clone());
+    Ok(Value::List(items))
+}
+
+/// The list without its last element.
+fn pop(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.pop", &args, 1)?;
+    let mut items = expect_list("lists.pop", &args, 0)?;
+    if items.pop().is_none() {
+        return Err(RuntimeError::new(ErrorKind::Index, "'lists.pop': la lista está vacía"));
+    }
+    Ok(Value::List(items))
+}
+
+/// `lists.insert(list, index, value)`: `value` ends up at `index`.
+fn insert(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.insert", &args, 3)?;
+    let mut items = expect_list("lists.insert", &args, 0)?;
+    let index = expect_int("lists.insert", &args, 1)?;
+    let at = position("lists.insert", index, items.len(), true)?;
+    items.insert(at, args[2].clone());
+    Ok(Value::List(items))
+}
+
+/// `lists.remove(list, index)`: the list without the element at `index`.
+fn remove(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.remove", &args, 2)?;
+    let mut items = expect_list("lists.remove", &args, 0)?;
+    let index = expect_int("lists.remove", &args, 1)?;
+    let at = position("lists.remove", index, items.len(), false)?;
+    items.remove(at);
+    Ok(Value::List(items))
+}
+
+/// Joins any number of lists.
+fn concat(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut joined = Vec::new();
+    for i in 0..args.len() {
+        joined.extend(expect_list("lists.concat", &args, i)?);
+    }
+    Ok(Value::List(joined))
+}
+
+/// `lists.sort(list)` uses `<`; `lists.sort(list, cmp)` calls `cmp(a, b)`,
+/// which returns either a `Bool` ("a goes first") or an `Int` whose sign
+/// orders `a` relative to `b`. The sort is stable.
+fn sort(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("lists.sort", &args, 1, 2)?;
+    let items = expect_list("lists.sort", &args, 0)?;
+    let comparator = match args.get(1) {
+        Some(_) => Some(expect_function("lists.sort", &args, 1)?),
+        None => None,
+    };
+    let mut before = |a: &Value, b: &Value| -> Result<bool, RuntimeError> {
+        let Some(cmp) = &comparator else {
+            return Ok(binary_op("<", a.clone(), b.clone())? == Value::Boolean(true));
+        };
+        match interpreter.call_value(cmp, vec![a.clone(), b.clone()])? {
+            Value::Boolean(first) => Ok(first),
+            Value::Integer(n) => Ok(n < 0),
+            other => Err(RuntimeError::type_error(format!(
+                "'lists.sort': el comparador debe devolver 'Bool' o 'Int', no '{}'",
+                Type::of(&other)
+            ))),
+        }
+    };
+    Ok(Value::List(merge_sort(items, &mut before)?))
+}
+
+/// A stable merge sort that tolerates a fallible (and possibly
+/// inconsistent) user comparator, unlike `slice::sort_by`.
+fn merge_sort(
+    mut items: Vec<Value>,
+    before: &mut dyn FnMut(&Value, &Value) -> Result<bool, RuntimeError>,
+) -> Result<Vec<Value>, RuntimeError> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, before)?;
+    let right = merge_sort(right, before)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Solo se adelanta el derecho si va estrictamente antes: estable.
+        if before(r, l)? {
+            merged.push(right.next().unwrap());
+        } else {
+            merged.push(left.next().unwrap());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+/// `lists.range(end)`, `lists.range(start, end)` or
+/// `lists.range(start, end, step)`; `end` is excluded.
+fn range(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("lists.range", &args, 1, 3)?;
+    let ints = (0..args.len()).map(|i| expect_int("lists.range", &args, i)).collect::<Result<Vec<_>, _>>()?;
+    let (start, end, step) = match ints.as_slice() {
+        [end] => (0, *end, 1),
+        [start, end] => (*start, *end, 1),
+        _ => (ints[0], ints[1], ints[2]),
+    };
+    if step == 0 {
+        return Err(RuntimeError::new(ErrorKind::Value, "'lists.range': el paso no puede ser 0"));
+    }
+    // En i128 ni la distancia ni el número de elementos pueden desbordarse.
+    let (start, end, step) = (start as i128, end as i128, step as i128);
+    let count = if (end - start).signum() == step.signum() { (end - start - step.signum()) / step + 1 } else { 0 };
+    if count > MAX_RANGE_ITEMS as i128 {
+        return Err(value_error(format!(
+            "'lists.range': el resultado superaría el máximo de {} elementos",
+            MAX_RANGE_ITEMS
+        )));
+    }
+    Ok(Value::List((0..count).map(|i| Value::Integer((start + i * step) as i64)).collect()))
+}
+
+/// Pairs up elements as `[a, b]` lists, stopping at the shorter list.
+fn zip(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.zip", &args, 2)?;
+    let left = expect_list("lists.zip", &args, 0)?;
+    let right = expect_list("lists.zip", &args, 1)?;
+    let pairs = left.into_iter().zip(right).map(|(a, b)| Value::List(vec![a, b])).collect();
+    Ok(Value::List(pairs))
+}
+
+fn map(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.map", &args, 2)?;
+    let items = expect_list("lists.map", &args, 0)?;
+    let f = expect_function("lists.map", &args, 1)?;
+    let mapped = items
+        .into_iter()
+        .map(|item| interpreter.call_value(&f, vec![item]))
+        .collect::<Result<_, _>>()?;
+    Ok(Value::List(mapped))
+}
+
+fn expect_predicate(name: &str, result: Value) -> Result<bool, RuntimeError> {
+    match result {
+        Value::Boolean(b) => Ok(b),
+        other => Err(RuntimeError::type_error(format!(
+            "'{}': la función debe devolver 'Bool', no '{}'",
+            name,
+            Type::of(&other)
+        ))),
+    }
+}
+
+fn filter(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.filter", &args, 2)?;
+    let items = expect_list("lists.filter", &args, 0)?;
+    let f = expect_function("lists.filter", &args, 1)?;
+    let mut kept = Vec::new();
+    for item in items {
+        if expect_predicate("lists.filter", interpreter.call_value(&f, vec![item.clone()])?)? {
+            kept.push(item);
+        }
+    }
+    Ok(Value::List(kept))
+}
+
+/// `lists.reduce(list, f, initial)` folds left with `f(acc, item)`. Without
+/// `initial` the first element is the starting value.
+fn reduce(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("lists.reduce", &args, 2, 3)?;
+    let items = expect_list("lists.reduce", &args, 0)?;
+    let f = expect_function("lists.reduce", &args, 1)?;
+    let mut items = items.into_iter();
+    let Some(mut acc) = args.get(2).cloned().or_else(|| items.next()) else {
+        return Err(RuntimeError::new(
+            ErrorKind::Value,
+            "'lists.reduce': la lista está vacía y no hay valor inicial",
+        ));
+    };
+    for item in items {
+        acc = interpreter.call_value(&f, vec![acc, item])?;
+    }
+    Ok(acc)
+}
+
+/// `lists.any` / `lists.all`, either over a list of `Bool`s or applying a
+/// predicate to each element. Both stop at the first decisive element.
+fn quantifier(interpreter: &mut Interpreter, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between(name, &args, 1, 2)?;
+    let items = expect_list(name, &args, 0)?;
+    let predicate = match args.get(1) {
+        Some(_) => Some(expect_function(name, &args, 1)?),
+        None => None,
+    };
+    let wanted = name == "lists.any";
+    for item in items {
+        let result = match &predicate {
+            Some(f) => interpreter.call_value(f, vec![item])?,
+            None => item,
+        };
+        if expect_predicate(name, result)? == wanted {
+            return Ok(Value::Boolean(wanted));
+        }
+    }
+    Ok(Value::Boolean(!wanted))
+}
+
+/// Adds the elements with `+`, so `Int` overflow is still reported. An
+/// empty list sums to 0.
+fn sum(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("lists.sum", &args, 1)?;
+    let items = expect_list("lists.sum", &args, 0)?;
+    let mut total = Value::Integer(0);
+    for (i, item) in items.into_iter().enumerate() {
+        if !is_number(&item) {
+            return Err(RuntimeError::type_error(format!(
+                "'lists.sum': el elemento {} es '{}', no un número",
+                i,
+                Type::of(&item)
+            )));
+        }
+        total = binary_op("+", total, item)?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_source, text};
+
+    fn ints(items: &[i64]) -> Value {
+        Value::List(items.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    fn range_of(bounds: &[i64]) -> Result<Value, RuntimeError> {
+        range(bounds.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    #[test]
+    fn range_counts_in_either_direction() {
+        assert_eq!(range_of(&[4]).unwrap(), ints(&[0, 1, 2, 3]));
+        assert_eq!(range_of(&[2, 5]).unwrap(), ints(&[2, 3, 4]));
+        assert_eq!(range_of(&[0, 10, 3]).unwrap(), ints(&[0, 3, 6, 9]));
+        assert_eq!(range_of(&[10, 0, -3]).unwrap(), ints(&[10, 7, 4, 1]));
+        assert_eq!(range_of(&[5, 5]).unwrap(), ints(&[]));
+        assert_eq!(range_of(&[5, 0]).unwrap(), ints(&[]));
+        assert_eq!(range_of(&[0, 5, -1]).unwrap(), ints(&[]));
+        assert_eq!(range_of(&[i64::MAX - 2, i64::MAX]).unwrap(), ints(&[i64::MAX - 2, i64::MAX - 1]));
+        assert_eq!(range_of(&[i64::MIN, i64::MAX, i64::MAX]).unwrap(), ints(&[i64::MIN, -1, i64::MAX - 1]));
+        assert_eq!(range_of(&[0, 1, 0]).unwrap_err().kind, ErrorKind::Value);
+    }
+
+    #[test]
+    fn huge_ranges_are_a_value_error() {
+        let max = MAX_RANGE_ITEMS as i64;
+        assert_eq!(range_of(&[0, 1_000_000_000_000]).unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(range_of(&[i64::MAX, i64::MIN, -1]).unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(range_of(&[0, max + 1]).unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(range_of(&[0, 2 * max + 1, 2]).unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(range_of(&[0, 1_000_000_000_000, 1_000_000_000_000 / 3]).unwrap(), ints(&[0, 333_333_333_333, 666_666_666_666, 999_999_999_999]));
+    }
+
+    #[test]
+    fn updates_return_a_new_list() {
+        let xs = ints(&[1, 2, 3]);
+        assert_eq!(push(vec![xs.clone(), Value::Integer(4)]).unwrap(), ints(&[1, 2, 3, 4]));
+        assert_eq!(pop(vec![xs.clone()]).unwrap(), ints(&[1, 2]));
+        assert_eq!(insert(vec![xs.clone(), Value::Integer(0), Value::Integer(0)]).unwrap(), ints(&[0, 1, 2, 3]));
+        assert_eq!(insert(vec![xs.clone(), Value::Integer(3), Value::Integer(4)]).unwrap(), ints(&[1, 2, 3, 4]));
+        assert_eq!(insert(vec![xs.clone(), Value::Integer(-1), Value::Integer(9)]).unwrap(), ints(&[1, 2, 9, 3]));
+        assert_eq!(remove(vec![xs.clone(), Value::Integer(-1)]).unwrap(), ints(&[1, 2]));
+        assert_eq!(remove(vec![xs.clone(), Value::Integer(0)]).unwrap(), ints(&[2, 3]));
+        assert_eq!(concat(vec![xs.clone(), ints(&[]), ints(&[4])]).unwrap(), ints(&[1, 2, 3, 4]));
+        assert_eq!(concat(vec![]).unwrap(), ints(&[]));
+
+        let errors = [
+            pop(vec![ints(&[])]),
+            insert(vec![xs.clone(), Value::Integer(4), Value::Integer(0)]),
+            insert(vec![xs.clone(), Value::Integer(-4), Value::Integer(0)]),
+            remove(vec![xs.clone(), Value::Integer(3)]),
+            remove(vec![ints(&[]), Value::Integer(0)]),
+        ];
+        for result in errors {
+            assert_eq!(result.unwrap_err().kind, ErrorKind::Index);
+        }
+        assert_eq!(push(vec![text("abc"), Value::Integer(1)]).unwrap_err().kind, ErrorKind::Type);
+        assert_eq!(concat(vec![xs, Value::Integer(1)]).unwrap_err().kind, ErrorKind::Type);
+    }
+
+    #[test]
+    fn natives_leave_their_arguments_untouched() {
+        let source = r#"
+fn main() -> int {
+    define xs::List<int> := [3, 1, 2];
+    define grown::List<int> := lists.push(xs, 4);
+    define sorted::List<int> := lists.sort(xs);
+    define flipped::List<int> := lists.reverse(xs);
+    lists.pop(xs);
+    lists.remove(xs, 0);
+    if xs != [3, 1, 2] { return 1 }
+    if grown != [3, 1, 2, 4] or sorted != [1, 2, 3] or flipped != [2, 1, 3] { return 2 }
+    if lists.len(xs) != 3 or lists.len([]) != 0 { return 3 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn sort_is_stable_and_takes_a_comparator() {
+        let source = r#"
+fn main() -> int {
+    if lists.sort([3, 1.5, 2]) != [1.5, 2, 3] or lists.sort(["b", "a", "c"]) != ["a", "b", "c"] { return 1 }
+    if lists.sort([3, 1, 2], fn(a::int, b::int) -> bool { return a > b }) != [3, 2, 1] { return 2 }
+    if lists.sort([3, 1, 2], fn(a::int, b::int) -> int { return a - b }) != [1, 2, 3] { return 3 }
+    define pairs::List<List<Any>> := [[2, "a"], [1, "b"], [2, "c"], [1, "d"]];
+    define by_key::List<List<Any>> := lists.sort(pairs, fn(a::List<Any>, b::List<Any>) -> int { return a[0] - b[0] });
+    if by_key != [[1, "b"], [1, "d"], [2, "a"], [2, "c"]] { return 4 }
+    try { lists.sort([1, "a"]); return 5 } catch e { if e["kind"] != "TypeError" { return 5 } }
+    try { lists.sort([1, 2], fn(a::int, b::int) -> Text { return "x" }); return 6 } catch e { if e["kind"] != "TypeError" { return 6 } }
+    try { lists.sort([1, 2], 3); return 7 } catch e { if e["kind"] != "TypeError" { return 7 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn higher_order_natives_call_back_into_the_script() {
+        let source = r#"
+fn main() -> int {
+    define xs::List<int> := [1, 2, 3, 4];
+    if lists.map(xs, fn(x::int) -> int { return x * x }) != [1, 4, 9, 16] { return 1 }
+    if lists.filter(xs, fn(x::int) -> bool { return x % 2 == 0 }) != [2, 4] { return 2 }
+    if lists.reduce(xs, fn(acc::int, x::int) -> int { return acc * 10 + x }) != 1234 { return 3 }
+    if lists.reduce([], fn(acc::int, x::int) -> int { return acc + x }, 7) != 7 { return 4 }
+    if not lists.any(xs, fn(x::int) -> bool { return x > 3 }) or lists.all(xs, fn(x::int) -> bool { return x > 3 }) { return 5 }
+    if lists.any([]) or not lists.all([]) or not lists.any([false, true]) or lists.all([true, false]) { return 6 }
+    ~> `any` se detiene en el primer `true`: el 0 nunca llega a dividir.
+    if not lists.any([1, 0], fn(x::int) -> bool { return 1 / x == 1 }) { return 7 }
+    try { lists.reduce([], fn(a::int, b::int) -> int { return a }); return 8 } catch e { if e["kind"] != "ValueError" { return 8 } }
+    try { lists.filter(xs, fn(x::int) -> int { return x }); return 9 } catch e { if e["kind"] != "TypeError" { return 9 } }
+    try { lists.all([true, 1]); return 10 } catch e { if e["kind"] != "TypeError" { return 10 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn searching_pairing_and_summing() {
+        let source = r#"
+fn main() -> int {
+    define xs::List<Any> := [1, "a", [2]];
+    if not lists.contains(xs, 1.0) or not lists.contains(xs, [2]) or lists.contains(xs, "b") { return 1 }
+    if lists.index_of(xs, "a") != 1 or lists.index_of(xs, "z") != -1 { return 2 }
+    if lists.zip([1, 2, 3], ["a", "b"]) != [[1, "a"], [2, "b"]] { return 3 }
+    if lists.enumerate(["x", "y"]) != [[0, "x"], [1, "y"]] { return 4 }
+    if lists.sum([]) != 0 or lists.sum([1, 2, 3]) != 6 or lists.sum([1, 0.5]) != 1.5 { return 5 }
+    if "{lists.sum([bigint("9223372036854775807"), 1])}" != "9223372036854775808" { return 6 }
+    try { lists.sum([9223372036854775807, 1]); return 7 } catch e { if e["kind"] != "OverflowError" { return 7 } }
+    try { lists.sum([1, "2"]); return 8 } catch e { if e["kind"] != "TypeError" { return 8 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+}
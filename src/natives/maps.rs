@@ -0,0 +1,249 @@
+//! The `maps` module. Like lists, maps are values: `set`, `remove` and
+//! `merge` return a new map (`m := maps.set(m, "k", 1)`). Every function
+//! keeps insertion order, so `keys`, `entries` and printing a map give the
+//! same result on every run; updating a key keeps its original position.
+
+use super::{arg_type_error, expect_args, expect_args_between, expect_function, expect_list, expect_text};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::{stringify, Interpreter};
+use crate::ordered_map::OrderedMap;
+use crate::types::Type;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("maps.len", |_, args| {
+        expect_args("maps.len", &args, 1)?;
+        Ok(Value::Integer(expect_map("maps.len", &args, 0)?.len() as i64))
+    });
+    interpreter.define_native("maps.keys", |_, args| {
+        expect_args("maps.keys", &args, 1)?;
+        let map = expect_map("maps.keys", &args, 0)?;
+        Ok(Value::List(map.into_iter().map(|(k, _)| Value::String(k)).collect()))
+    });
+    interpreter.define_native("maps.values", |_, args| {
+        expect_args("maps.values", &args, 1)?;
+        let map = expect_map("maps.values", &args, 0)?;
+        Ok(Value::List(map.into_iter().map(|(_, v)| v).collect()))
+    });
+    interpreter.define_native("maps.entries", |_, args| {
+        expect_args("maps.entries", &args, 1)?;
+        let map = expect_map("maps.entries", &args, 0)?;
+        Ok(Value::List(map.into_iter().map(|(k, v)| Value::List(vec![Value::String(k), v])).collect()))
+    });
+    interpreter.define_native("maps.from_entries", |_, args| from_entries(args));
+    interpreter.define_native("maps.has", |_, args| {
+        expect_args("maps.has", &args, 2)?;
+        let map = expect_map("maps.has", &args, 0)?;
+        Ok(Value::Boolean(map.contains_key(&expect_text("maps.has", &args, 1)?)))
+    });
+    interpreter.define_native("maps.get", |_, args| get(args));
+    interpreter.define_native("maps.set", |_, args| {
+        expect_args("maps.set", &args, 3)?;
+        let mut map = expect_map("maps.set", &args, 0)?;
+        map.insert(expect_text("maps.set", &args, 1)?, args[2].clone());
+        Ok(Value::Map(map))
+    });
+    interpreter.define_native("maps.remove", |_, args| remove(args));
+    interpreter.define_native("maps.merge", |_, args| merge(args));
+    interpreter.define_native("maps.map_values", map_values);
+    interpreter.define_native("maps.filter", filter);
+}
+
+fn expect_map(name: &str, args: &[Value], index: usize) -> Result<OrderedMap<Value>, RuntimeError> {
+    match &args[index] {
+        Value::Map(map) => Ok(map.clone()),
+        other => Err(arg_type_error(name, index, "'Map'", other)),
+    }
+}
+
+fn missing_key(name: &str, key: &str) -> RuntimeError {
+    RuntimeError::new(ErrorKind::Key, format!("'{}': la clave '{}' no existe en el mapa", name, key))
+}
+
+/// `maps.get(map, key)` fails on a missing key like `map[key]` does;
+/// `maps.get(map, key, default)` returns `default` instead.
+fn get(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("maps.get", &args, 2, 3)?;
+    let map = expect_map("maps.get", &args, 0)?;
+    let key = expect_text("maps.get", &args, 1)?;
+    match (map.get(&key), args.get(2)) {
+        (Some(value), _) => Ok(value.clone()),
+        (None, Some(default)) => Ok(default.clone()),
+        (None, None) => Err(missing_key("maps.get", &key)),
+    }
+}
+
+/// The map without `key`; removing a missing key is a `KeyError`.
+fn remove(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("maps.remove", &args, 2)?;
+    let mut map = expect_map("maps.remove", &args, 0)?;
+    let key = expect_text("maps.remove", &args, 1)?;
+    map.remove(&key).ok_or_else(|| missing_key("maps.remove", &key))?;
+    Ok(Value::Map(map))
+}
+
+/// Merges any number of maps left to right. Later values win, but a key
+/// stays where it first appeared.
+fn merge(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut merged = OrderedMap::new();
+    for i in 0..args.len() {
+        for (k, v) in expect_map("maps.merge", &args, i)? {
+            merged.insert(k, v);
+        }
+    }
+    Ok(Value::Map(merged))
+}
+
+/// Builds a map from `[key, value]` pairs, the inverse of `maps.entries`.
+fn from_entries(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("maps.from_entries", &args, 1)?;
+    let mut map = OrderedMap::new();
+    for (i, entry) in expect_list("maps.from_entries", &args, 0)?.into_iter().enumerate() {
+        if let Value::List(pair) = &entry
+            && let [Value::String(key), value] = pair.as_slice()
+        {
+            map.insert(key.clone(), value.clone());
+            continue;
+        }
+        return Err(RuntimeError::type_error(format!(
+            "'maps.from_entries': el elemento {} debe ser un par [Text, valor], no '{}'",
+            i,
+            stringify(&entry)
+        )));
+    }
+    Ok(Value::Map(map))
+}
+
+/// Applies `f(value)` to every entry, keeping the keys.
+fn map_values(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("maps.map_values", &args, 2)?;
+    let map = expect_map("maps.map_values", &args, 0)?;
+    let f = expect_function("maps.map_values", &args, 1)?;
+    let mut mapped = OrderedMap::new();
+    for (k, v) in map {
+        mapped.insert(k, interpreter.call_value(&f, vec![v])?);
+    }
+    Ok(Value::Map(mapped))
+}
+
+/// Keeps the entries for which `f(key, value)` returns `true`.
+fn filter(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("maps.filter", &args, 2)?;
+    let map = expect_map("maps.filter", &args, 0)?;
+    let f = expect_function("maps.filter", &args, 1)?;
+    let mut kept = OrderedMap::new();
+    for (k, v) in map {
+        match interpreter.call_value(&f, vec![Value::String(k.clone()), v.clone()])? {
+            Value::Boolean(true) => {
+                kept.insert(k, v);
+            }
+            Value::Boolean(false) => {}
+            other => {
+                return Err(RuntimeError::type_error(format!(
+                    "'maps.filter': la función debe devolver 'Bool', no '{}'",
+                    Type::of(&other)
+                )));
+            }
+        }
+    }
+    Ok(Value::Map(kept))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_source, text};
+
+    fn map(entries: &[(&str, i64)]) -> Value {
+        Value::Map(entries.iter().map(|&(k, v)| (k.to_string(), Value::Integer(v))).collect())
+    }
+
+    fn keys(value: &Value) -> Vec<String> {
+        match value {
+            Value::Map(map) => map.clone().into_iter().map(|(k, _)| k).collect(),
+            other => panic!("se esperaba un mapa, no {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lookups_fall_back_to_the_default_or_fail_with_a_key_error() {
+        let m = map(&[("a", 1), ("b", 2)]);
+        assert_eq!(get(vec![m.clone(), text("a")]).unwrap(), Value::Integer(1));
+        assert_eq!(get(vec![m.clone(), text("z"), Value::Integer(0)]).unwrap(), Value::Integer(0));
+        assert_eq!(get(vec![m.clone(), text("b"), Value::Integer(0)]).unwrap(), Value::Integer(2));
+        assert_eq!(get(vec![m.clone(), text("z")]).unwrap_err().kind, ErrorKind::Key);
+        assert_eq!(remove(vec![m.clone(), text("z")]).unwrap_err().kind, ErrorKind::Key);
+        assert_eq!(get(vec![m.clone(), Value::Integer(1)]).unwrap_err().kind, ErrorKind::Type);
+        assert_eq!(get(vec![text("a"), text("a")]).unwrap_err().kind, ErrorKind::Type);
+        assert_eq!(get(vec![m]).unwrap_err().kind, ErrorKind::Arity);
+    }
+
+    #[test]
+    fn updates_keep_the_insertion_order() {
+        let m = map(&[("b", 1), ("a", 2), ("c", 3)]);
+        assert_eq!(keys(&remove(vec![m.clone(), text("a")]).unwrap()), ["b", "c"]);
+
+        let merged = merge(vec![m.clone(), map(&[("d", 4), ("a", 20)]), map(&[("b", 10)])]).unwrap();
+        assert_eq!(keys(&merged), ["b", "a", "c", "d"]);
+        assert_eq!(merged, map(&[("b", 10), ("a", 20), ("c", 3), ("d", 4)]));
+        assert_eq!(merge(vec![]).unwrap(), map(&[]));
+        assert_eq!(merge(vec![m.clone(), Value::Integer(1)]).unwrap_err().kind, ErrorKind::Type);
+
+        let pairs = Value::List(vec![
+            Value::List(vec![text("y"), Value::Integer(1)]),
+            Value::List(vec![text("x"), Value::Integer(2)]),
+            Value::List(vec![text("y"), Value::Integer(3)]),
+        ]);
+        assert_eq!(from_entries(vec![pairs]).unwrap(), map(&[("y", 3), ("x", 2)]));
+        let bad = [
+            Value::List(vec![Value::Integer(1)]),
+            Value::List(vec![Value::List(vec![text("k")])]),
+            Value::List(vec![Value::List(vec![Value::Integer(1), Value::Integer(2)])]),
+        ];
+        for entries in bad {
+            assert_eq!(from_entries(vec![entries]).unwrap_err().kind, ErrorKind::Type);
+        }
+    }
+
+    #[test]
+    fn natives_leave_their_arguments_untouched() {
+        let source = r#"
+fn main() -> int {
+    define m::Map<Text, int> := { "b": 1, "a": 2 };
+    define added::Map<Text, int> := maps.set(m, "c", 3);
+    define updated::Map<Text, int> := maps.set(m, "b", 10);
+    define removed::Map<Text, int> := maps.remove(m, "b");
+    define merged::Map<Text, int> := maps.merge(m, { "z": 0 });
+    if m != { "b": 1, "a": 2 } or maps.len(m) != 2 { return 1 }
+    if maps.keys(added) != ["b", "a", "c"] or maps.keys(updated) != ["b", "a"] or updated["b"] != 10 { return 2 }
+    if maps.keys(removed) != ["a"] or maps.len(merged) != 3 { return 3 }
+    if maps.values(m) != [1, 2] or maps.entries(m) != [["b", 1], ["a", 2]] { return 4 }
+    if maps.from_entries(maps.entries(m)) != m or maps.len({}) != 0 { return 5 }
+    if not maps.has(m, "a") or maps.has(m, "c") { return 6 }
+    try { maps.get(m, "c"); return 7 } catch e { if e["kind"] != "KeyError" { return 7 } }
+    try { maps.set(m, 1, 1); return 8 } catch e { if e["kind"] != "TypeError" { return 8 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn higher_order_natives_call_back_into_the_script() {
+        let source = r#"
+fn main() -> int {
+    define m::Map<Text, int> := { "c": 3, "a": 1, "b": 2 };
+    define doubled::Map<Text, int> := maps.map_values(m, fn(v::int) -> int { return v * 2 });
+    if maps.keys(doubled) != ["c", "a", "b"] or maps.values(doubled) != [6, 2, 4] { return 1 }
+    define odd::Map<Text, int> := maps.filter(m, fn(k::Text, v::int) -> bool { return v % 2 == 1 });
+    if maps.keys(odd) != ["c", "a"] { return 2 }
+    if maps.len(maps.filter(m, fn(k::Text, v::int) -> bool { return k == "b" })) != 1 { return 3 }
+    try { maps.filter(m, fn(k::Text, v::int) -> int { return v }); return 4 } catch e { if e["kind"] != "TypeError" { return 4 } }
+    try { maps.map_values(m, 1); return 5 } catch e { if e["kind"] != "TypeError" { return 5 } }
+    try { maps.map_values(m, fn(v::int) -> int { return 1 / (v - 2) }); return 6 } catch e { if e["kind"] == "TypeError" { return 6 } }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+}
@@ -0,0 +1,325 @@
+//! The `math` module. `math.add`, `math.sub` and `math.mul` follow the
+//! operators: `Int` results are overflow-checked, `BigInt` and `Float`
+//! operands widen the result.
+//!
+//! Division does not follow the operators. `/` and `%` on integers
+//! truncate toward zero, while `math.div` is always float division and
+//! `math.idiv` and `math.mod` are the floored pair, so
+//! `idiv(a, b) * b + mod(a, b) == a` and `mod` takes the sign of the
+//! divisor: `-7 / 2` is `-3` and `-7 % 2` is `-1`, but `math.div(-7, 2)`
+//! is `-3.5`, `math.idiv(-7, 2)` is `-4` and `math.mod(-7, 2)` is `1`.
+
+use std::cmp::Ordering;
+
+use super::{arg_type_error, expect_args, expect_args_between, expect_int, expect_number, float_to_int, is_number, value_error};
+use crate::ast::Value;
+use crate::bigint::BigInt;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::{binary_op, overflow_error, Interpreter};
+
+/// Largest `BigInt` (in decimal digits) that `pow` will build.
+const MAX_POW_DIGITS: usize = 100_000;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_constant("math.pi", Value::Float(std::f64::consts::PI));
+    interpreter.define_constant("math.e", Value::Float(std::f64::consts::E));
+    interpreter.define_constant("math.tau", Value::Float(std::f64::consts::TAU));
+
+    interpreter.define_native("math.add", |_, args| arithmetic("math.add", "+", args));
+    interpreter.define_native("math.sub", |_, args| arithmetic("math.sub", "-", args));
+    interpreter.define_native("math.mul", |_, args| arithmetic("math.mul", "*", args));
+    interpreter.define_native("math.div", |_, args| div(args));
+    interpreter.define_native("math.idiv", |_, args| floored("math.idiv", args));
+    interpreter.define_native("math.mod", |_, args| floored("math.mod", args));
+    interpreter.define_native("math.pow", |_, args| pow(args));
+    interpreter.define_native("math.abs", |_, args| abs(args));
+    interpreter.define_native("math.floor", |_, args| to_int("math.floor", args, f64::floor));
+    interpreter.define_native("math.ceil", |_, args| to_int("math.ceil", args, f64::ceil));
+    interpreter.define_native("math.round", |_, args| round(args));
+    interpreter.define_native("math.min", |_, args| extreme("math.min", args, Ordering::Less));
+    interpreter.define_native("math.max", |_, args| extreme("math.max", args, Ordering::Greater));
+
+    interpreter.define_native("math.sqrt", |_, args| float_fn("math.sqrt", args, f64::sqrt));
+    interpreter.define_native("math.exp", |_, args| float_fn("math.exp", args, f64::exp));
+    interpreter.define_native("math.log10", |_, args| float_fn("math.log10", args, f64::log10));
+    interpreter.define_native("math.log2", |_, args| float_fn("math.log2", args, f64::log2));
+    interpreter.define_native("math.log", |_, args| log(args));
+    interpreter.define_native("math.sin", |_, args| float_fn("math.sin", args, f64::sin));
+    interpreter.define_native("math.cos", |_, args| float_fn("math.cos", args, f64::cos));
+    interpreter.define_native("math.tan", |_, args| float_fn("math.tan", args, f64::tan));
+    interpreter.define_native("math.asin", |_, args| float_fn("math.asin", args, f64::asin));
+    interpreter.define_native("math.acos", |_, args| float_fn("math.acos", args, f64::acos));
+    interpreter.define_native("math.atan", |_, args| float_fn("math.atan", args, f64::atan));
+    interpreter.define_native("math.atan2", |_, args| atan2(args));
+}
+
+fn expect_numbers(name: &str, args: &[Value]) -> Result<(), RuntimeError> {
+    match args.iter().position(|v| !is_number(v)) {
+        Some(i) => Err(arg_type_error(name, i, "un número", &args[i])),
+        None => Ok(()),
+    }
+}
+
+fn arithmetic(name: &str, op: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args(name, &args, 2)?;
+    expect_numbers(name, &args)?;
+    let mut args = args.into_iter();
+    binary_op(op, args.next().unwrap(), args.next().unwrap())
+}
+
+/// `math.div(a, b)`: `a / b` as a `Float`, even for two `Int`s, where the
+/// `/` operator would truncate.
+fn div(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("math.div", &args, 2)?;
+    let (a, b) = (expect_number("math.div", &args, 0)?, expect_number("math.div", &args, 1)?);
+    if b == 0.0 {
+        return Err(RuntimeError::new(ErrorKind::Arithmetic, "División por cero"));
+    }
+    Ok(Value::Float(a / b))
+}
+
+/// `math.idiv` and `math.mod`: division rounding toward negative infinity
+/// and the matching remainder, which takes the sign of the divisor. They
+/// differ from `/` and `%`, which truncate, when the signs differ.
+fn floored(name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args(name, &args, 2)?;
+    expect_numbers(name, &args)?;
+    let want_quotient = name == "math.idiv";
+    match (&args[0], &args[1]) {
+        (Value::Integer(_) | Value::BigInt(_), Value::Integer(_) | Value::BigInt(_)) => {
+            let (a, b) = (big(&args[0]), big(&args[1]));
+            let (mut q, mut r) = a
+                .div_rem(&b)
+                .ok_or_else(|| RuntimeError::new(ErrorKind::Arithmetic, "División por cero"))?;
+            let zero = BigInt::zero();
+            if !r.is_zero() && (r < zero) != (b < zero) {
+                q = &q - &BigInt::from_i64(1);
+                r = &r + &b;
+            }
+            let result = if want_quotient { q } else { r };
+            // Con dos 'Int' el resultado sigue siendo 'Int' (o desborda).
+            if let (Value::Integer(x), Value::Integer(y)) = (&args[0], &args[1]) {
+                return result
+                    .to_i64()
+                    .map(Value::Integer)
+                    .ok_or_else(|| overflow_error(format!("{}({}, {})", name, x, y)));
+            }
+            Ok(Value::BigInt(result))
+        }
+        _ => {
+            let (a, b) = (expect_number(name, &args, 0)?, expect_number(name, &args, 1)?);
+            if b == 0.0 {
+                return Err(RuntimeError::new(ErrorKind::Arithmetic, "División por cero"));
+            }
+            let q = (a / b).floor();
+            Ok(Value::Float(if want_quotient { q } else { a - q * b }))
+        }
+    }
+}
+
+fn big(value: &Value) -> BigInt {
+    match value {
+        Value::BigInt(n) => n.clone(),
+        Value::Integer(n) => BigInt::from_i64(*n),
+        _ => BigInt::zero(),
+    }
+}
+
+/// Integer powers stay exact (`Int` overflow-checked, `BigInt` up to
+/// `MAX_POW_DIGITS` digits); negative or fractional exponents produce a
+/// `Float`.
+fn pow(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("math.pow", &args, 2)?;
+    expect_numbers("math.pow", &args)?;
+    match (&args[0], &args[1]) {
+        (Value::Integer(base), Value::Integer(exp)) if *exp >= 0 => u32::try_from(*exp)
+            .ok()
+            .and_then(|exp| base.checked_pow(exp))
+            .map(Value::Integer)
+            .ok_or_else(|| overflow_error(format!("math.pow({}, {})", base, exp))),
+        (Value::BigInt(base), Value::Integer(exp)) if *exp >= 0 => {
+            if *exp as f64 * base.log10() > MAX_POW_DIGITS as f64 {
+                return Err(value_error(format!(
+                    "'math.pow': el resultado tendría más de {} dígitos",
+                    MAX_POW_DIGITS
+                )));
+            }
+            let (mut result, mut square, mut exp) = (BigInt::from_i64(1), base.clone(), *exp);
+            while exp > 0 {
+                if exp & 1 == 1 {
+                    result = &result * &square;
+                }
+                exp >>= 1;
+                if exp > 0 {
+                    square = &square * &square;
+                }
+            }
+            Ok(Value::BigInt(result))
+        }
+        _ => {
+            let (base, exp) = (expect_number("math.pow", &args, 0)?, expect_number("math.pow", &args, 1)?);
+            finite("math.pow", base, base.powf(exp))
+        }
+    }
+}
+
+fn abs(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("math.abs", &args, 1)?;
+    match &args[0] {
+        Value::Integer(n) => n
+            .checked_abs()
+            .map(Value::Integer)
+            .ok_or_else(|| overflow_error(format!("math.abs({})", n))),
+        Value::BigInt(n) if *n < BigInt::zero() => Ok(Value::BigInt(-n)),
+        Value::BigInt(n) => Ok(Value::BigInt(n.clone())),
+        _ => Ok(Value::Float(expect_number("math.abs", &args, 0)?.abs())),
+    }
+}
+
+/// `math.floor` and `math.ceil`: integers pass through, floats are rounded
+/// with `f` and converted to `Int`.
+fn to_int(name: &str, args: Vec<Value>, f: fn(f64) -> f64) -> Result<Value, RuntimeError> {
+    expect_args(name, &args, 1)?;
+    match &args[0] {
+        Value::Integer(_) | Value::BigInt(_) => Ok(args[0].clone()),
+        _ => float_to_int(name, f(expect_number(name, &args, 0)?)),
+    }
+}
+
+/// `math.round(x)` rounds half away from zero to an `Int`;
+/// `math.round(x, digits)` keeps a `Float` with that many decimals.
+fn round(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("math.round", &args, 1, 2)?;
+    if args.len() == 1 {
+        return to_int("math.round", args, f64::round);
+    }
+    let x = expect_number("math.round", &args, 0)?;
+    let digits = expect_int("math.round", &args, 1)?;
+    // Más allá de 15 decimales un f64 ya no cambia al redondear.
+    let factor = 10f64.powi(digits.clamp(-308, 15) as i32);
+    Ok(Value::Float((x * factor).round() / factor))
+}
+
+/// `math.min` / `math.max` over several numbers or a single list of them.
+/// The winning value is returned unchanged, so `Int`s stay `Int`s.
+fn extreme(name: &str, args: Vec<Value>, wanted: Ordering) -> Result<Value, RuntimeError> {
+    let values = match args.as_slice() {
+        [Value::List(items)] => items.clone(),
+        _ => args,
+    };
+    if values.is_empty() {
+        return Err(RuntimeError::new(
+            ErrorKind::Arity,
+            format!("'{}' necesita al menos un número", name),
+        ));
+    }
+    expect_numbers(name, &values)?;
+    let op = if wanted == Ordering::Less { "<" } else { ">" };
+    let mut best = values[0].clone();
+    for value in &values[1..] {
+        if binary_op(op, value.clone(), best.clone())? == Value::Boolean(true) {
+            best = value.clone();
+        }
+    }
+    Ok(best)
+}
+
+/// A one-argument float function; a NaN or infinite result from a finite
+/// input (`sqrt(-1)`, `log(0)`, `asin(2)`) is an `ArithmeticError`.
+fn float_fn(name: &str, args: Vec<Value>, f: fn(f64) -> f64) -> Result<Value, RuntimeError> {
+    expect_args(name, &args, 1)?;
+    let x = expect_number(name, &args, 0)?;
+    finite(name, x, f(x))
+}
+
+fn finite(name: &str, input: f64, result: f64) -> Result<Value, RuntimeError> {
+    if input.is_finite() && !result.is_finite() {
+        return Err(RuntimeError::new(
+            ErrorKind::Arithmetic,
+            format!("'{}' no tiene un resultado finito para {:?}", name, input),
+        ));
+    }
+    Ok(Value::Float(result))
+}
+
+/// `math.log(x)` is the natural logarithm; `math.log(x, base)` uses `base`.
+fn log(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("math.log", &args, 1, 2)?;
+    let x = expect_number("math.log", &args, 0)?;
+    if args.len() == 1 {
+        return finite("math.log", x, x.ln());
+    }
+    let base = expect_number("math.log", &args, 1)?;
+    if base <= 0.0 || base == 1.0 {
+        return Err(RuntimeError::new(
+            ErrorKind::Arithmetic,
+            format!("'math.log': la base {:?} no es válida", base),
+        ));
+    }
+    finite("math.log", x, x.log(base))
+}
+
+fn atan2(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("math.atan2", &args, 2)?;
+    let y = expect_number("math.atan2", &args, 0)?;
+    let x = expect_number("math.atan2", &args, 1)?;
+    Ok(Value::Float(y.atan2(x)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, a: Value, b: Value) -> Value {
+        match name {
+            "math.div" => div(vec![a, b]),
+            _ => floored(name, vec![a, b]),
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn division_floors_where_the_operators_truncate() {
+        let (a, b) = (Value::Integer(-7), Value::Integer(2));
+        assert_eq!(binary_op("/", a.clone(), b.clone()).unwrap(), Value::Integer(-3));
+        assert_eq!(binary_op("%", a.clone(), b.clone()).unwrap(), Value::Integer(-1));
+        assert_eq!(call("math.div", a.clone(), b.clone()), Value::Float(-3.5));
+        assert_eq!(call("math.idiv", a.clone(), b.clone()), Value::Integer(-4));
+        assert_eq!(call("math.mod", a, b), Value::Integer(1));
+        assert_eq!(call("math.mod", Value::Integer(7), Value::Integer(-2)), Value::Integer(-1));
+        assert_eq!(call("math.mod", Value::Float(-7.5), Value::Integer(2)), Value::Float(0.5));
+    }
+
+    #[test]
+    fn bigint_powers_are_exact_up_to_a_size() {
+        let big = |text: &str| Value::BigInt(BigInt::parse(text).unwrap());
+        let power = |base: Value, exp: i64| pow(vec![base, Value::Integer(exp)]);
+        assert_eq!(power(big("2"), 100).unwrap(), big("1267650600228229401496703205376"));
+        assert_eq!(power(big("-3"), 3).unwrap(), big("-27"));
+        assert_eq!(power(big("7"), 0).unwrap(), big("1"));
+        assert_eq!(power(big("1"), i64::MAX).unwrap(), big("1"));
+        assert_eq!(power(big("-1"), i64::MAX).unwrap(), big("-1"));
+        assert_eq!(power(big("0"), i64::MAX).unwrap(), big("0"));
+        let Value::BigInt(huge) = power(big("10"), 5_000).unwrap() else { panic!("se esperaba un BigInt") };
+        assert_eq!(huge.to_string().len(), 5_001);
+
+        for (base, exp) in [("2", 1_000_000_000_000), ("10", MAX_POW_DIGITS as i64 + 1), ("123456789123456789", 6_000)] {
+            let err = power(big(base), exp).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Value, "{}^{}", base, exp);
+        }
+        assert_eq!(power(Value::Integer(2), 64).unwrap_err().kind, ErrorKind::Overflow);
+    }
+
+    #[test]
+    fn floored_pair_rebuilds_the_dividend() {
+        for a in -9..=9 {
+            for b in [-4, -3, -1, 1, 2, 5] {
+                let q = call("math.idiv", Value::Integer(a), Value::Integer(b));
+                let r = call("math.mod", Value::Integer(a), Value::Integer(b));
+                let (Value::Integer(q), Value::Integer(r)) = (q, r) else { panic!("{} / {}", a, b) };
+                assert_eq!(q * b + r, a);
+                assert!(r == 0 || (r < 0) == (b < 0));
+            }
+        }
+    }
+}
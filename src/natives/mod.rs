@@ -0,0 +1,143 @@
+//! Native functions available to every Lunaria program, grouped by module.
+//! Each module file exposes a `register` function that adds its natives
+//! (and constants) to an interpreter under `module.name`.
+
+mod env;
+mod fs;
+mod json;
+mod lists;
+mod maps;
+mod math;
+mod random;
+mod shell;
+mod strings;
+mod time;
+
+use crate::ast::Value;
+use crate::bigint::BigInt;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::{stringify, Interpreter};
+use crate::types::Type;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("console.out", |_, args| Ok(console_out(args)));
+    interpreter.define_native("bigint", |_, args| bigint(args));
+    math::register(interpreter);
+    random::register(interpreter);
+    shell::register(interpreter);
+    strings::register(interpreter);
+    time::register(interpreter);
+    env::register(interpreter);
+    fs::register(interpreter);
+    json::register(interpreter);
+    lists::register(interpreter);
+    maps::register(interpreter);
+}
+
+fn console_out(args: Vec<Value>) -> Value {
+    for val in args {
+        print!("{}", stringify(&val));
+    }
+    println!();
+    Value::Void
+}
+
+fn bigint(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("bigint", &args, 1)?;
+    match &args[0] {
+        Value::Integer(n) => Ok(Value::BigInt(BigInt::from_i64(*n))),
+        Value::BigInt(n) => Ok(Value::BigInt(n.clone())),
+        Value::String(text) => BigInt::parse(text.trim()).map(Value::BigInt).ok_or_else(|| {
+            RuntimeError::new(ErrorKind::Value, format!("'{}' no es un entero válido", text))
+        }),
+        other => Err(arg_type_error("bigint", 0, "'Int' o 'Text'", other)),
+    }
+}
+
+/// Fails unless exactly `count` arguments were passed to `name`.
+fn expect_args(name: &str, args: &[Value], count: usize) -> Result<(), RuntimeError> {
+    expect_args_between(name, args, count, count)
+}
+
+/// Fails unless between `min` and `max` arguments were passed to `name`.
+fn expect_args_between(name: &str, args: &[Value], min: usize, max: usize) -> Result<(), RuntimeError> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let expected = if min == max { min.to_string() } else { format!("entre {} y {}", min, max) };
+    Err(RuntimeError::new(
+        ErrorKind::Arity,
+        format!("'{}' espera {} argumento(s) pero recibió {}", name, expected, args.len()),
+    ))
+}
+
+/// The error for argument `index` (0-based) of `name` having the wrong
+/// type; `expected` is a description such as `"'Int'"` or `"un número"`.
+fn arg_type_error(name: &str, index: usize, expected: &str, value: &Value) -> RuntimeError {
+    RuntimeError::type_error(format!(
+        "'{}': el argumento {} espera {} pero recibió '{}'",
+        name,
+        index + 1,
+        expected,
+        Type::of(value)
+    ))
+}
+
+fn is_number(value: &Value) -> bool {
+    matches!(value, Value::Integer(_) | Value::Float(_) | Value::BigInt(_))
+}
+
+/// Argument `index` of `name` as a float; integers are widened.
+fn expect_number(name: &str, args: &[Value], index: usize) -> Result<f64, RuntimeError> {
+    match &args[index] {
+        Value::Integer(n) => Ok(*n as f64),
+        Value::Float(f) => Ok(*f),
+        Value::BigInt(n) => Ok(n.to_f64()),
+        other => Err(arg_type_error(name, index, "un número", other)),
+    }
+}
+
+fn expect_int(name: &str, args: &[Value], index: usize) -> Result<i64, RuntimeError> {
+    match &args[index] {
+        Value::Integer(n) => Ok(*n),
+        other => Err(arg_type_error(name, index, "'Int'", other)),
+    }
+}
+
+fn expect_list(name: &str, args: &[Value], index: usize) -> Result<Vec<Value>, RuntimeError> {
+    match &args[index] {
+        Value::List(items) => Ok(items.clone()),
+        other => Err(arg_type_error(name, index, "'List'", other)),
+    }
+}
+
+fn expect_text(name: &str, args: &[Value], index: usize) -> Result<String, RuntimeError> {
+    match &args[index] {
+        Value::String(text) => Ok(text.clone()),
+        other => Err(arg_type_error(name, index, "'Text'", other)),
+    }
+}
+
+fn expect_function(name: &str, args: &[Value], index: usize) -> Result<Value, RuntimeError> {
+    match &args[index] {
+        Value::Function(_) => Ok(args[index].clone()),
+        other => Err(arg_type_error(name, index, "'Fn'", other)),
+    }
+}
+
+fn value_error(message: String) -> RuntimeError {
+    RuntimeError::new(ErrorKind::Value, message)
+}
+
+/// `value` as an `Int`, or an OverflowError naming `name` when it is not
+/// finite or does not fit.
+fn float_to_int(name: &str, value: f64) -> Result<Value, RuntimeError> {
+    // 2^63 es el primer valor que ya no cabe en un i64.
+    if !value.is_finite() || value.abs() >= 9_223_372_036_854_775_808.0 {
+        return Err(RuntimeError::new(
+            ErrorKind::Overflow,
+            format!("'{}': {:?} no cabe en 'Int'", name, value),
+        ));
+    }
+    Ok(Value::Integer(value as i64))
+}
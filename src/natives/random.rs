@@ -0,0 +1,196 @@
+//! The `random` module. All natives draw from `Interpreter::rng`, so a
+//! `--seed=N` flag or `random.seed(n)` makes a run reproducible. Like the
+//! rest of the language, `shuffle` and `sample` return new lists.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::{expect_args, expect_args_between, expect_int, expect_list, expect_number};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::Interpreter;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("random.int", int);
+    interpreter.define_native("random.float", float);
+    interpreter.define_native("random.choice", choice);
+    interpreter.define_native("random.shuffle", shuffle);
+    interpreter.define_native("random.sample", sample);
+    interpreter.define_native("random.seed", seed);
+}
+
+/// `random.int(lo, hi)`: an integer in `lo..=hi`.
+fn int(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("random.int", &args, 2)?;
+    let lo = expect_int("random.int", &args, 0)?;
+    let hi = expect_int("random.int", &args, 1)?;
+    if lo > hi {
+        return Err(RuntimeError::new(
+            ErrorKind::Value,
+            format!("'random.int': el mínimo {} es mayor que el máximo {}", lo, hi),
+        ));
+    }
+    Ok(Value::Integer(interpreter.rng.gen_range(lo..=hi)))
+}
+
+/// `random.float()` is in `[0, 1)`; `random.float(lo, hi)` in `[lo, hi)`.
+fn float(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("random.float", &args, 0, 2)?;
+    let unit: f64 = interpreter.rng.r#gen();
+    if args.is_empty() {
+        return Ok(Value::Float(unit));
+    }
+    expect_args("random.float", &args, 2)?;
+    let lo = expect_number("random.float", &args, 0)?;
+    let hi = expect_number("random.float", &args, 1)?;
+    if lo > hi {
+        return Err(RuntimeError::new(
+            ErrorKind::Value,
+            format!("'random.float': el mínimo {:?} es mayor que el máximo {:?}", lo, hi),
+        ));
+    }
+    Ok(Value::Float(lo + unit * (hi - lo)))
+}
+
+fn choice(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("random.choice", &args, 1)?;
+    let items = expect_list("random.choice", &args, 0)?;
+    items.choose(&mut interpreter.rng).cloned().ok_or_else(|| {
+        RuntimeError::new(ErrorKind::Value, "'random.choice': la lista está vacía")
+    })
+}
+
+fn shuffle(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("random.shuffle", &args, 1)?;
+    let mut items = expect_list("random.shuffle", &args, 0)?;
+    items.shuffle(&mut interpreter.rng);
+    Ok(Value::List(items))
+}
+
+/// `random.sample(list, n)`: `n` elements picked without repetition.
+fn sample(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("random.sample", &args, 2)?;
+    let items = expect_list("random.sample", &args, 0)?;
+    let n = expect_int("random.sample", &args, 1)?;
+    if n < 0 || n as usize > items.len() {
+        return Err(RuntimeError::new(
+            ErrorKind::Value,
+            format!("'random.sample': no se pueden tomar {} elementos de una lista de {}", n, items.len()),
+        ));
+    }
+    let picked = items.choose_multiple(&mut interpreter.rng, n as usize).cloned().collect();
+    Ok(Value::List(picked))
+}
+
+fn seed(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("random.seed", &args, 1)?;
+    let seed = expect_int("random.seed", &args, 0)?;
+    interpreter.seed_random(seed as u64);
+    Ok(Value::Void)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::run_source;
+
+    fn ints(items: &[i64]) -> Value {
+        Value::List(items.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    /// One draw from every native, in a fixed order.
+    fn draws(interpreter: &mut Interpreter) -> Vec<Value> {
+        let list = ints(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        vec![
+            int(interpreter, vec![Value::Integer(0), Value::Integer(1_000_000)]).unwrap(),
+            float(interpreter, vec![]).unwrap(),
+            float(interpreter, vec![Value::Integer(-5), Value::Float(5.0)]).unwrap(),
+            choice(interpreter, vec![list.clone()]).unwrap(),
+            shuffle(interpreter, vec![list.clone()]).unwrap(),
+            sample(interpreter, vec![list, Value::Integer(3)]).unwrap(),
+        ]
+    }
+
+    fn sorted(list: Value) -> Vec<i64> {
+        let mut numbers = expect_list("", &[list], 0)
+            .unwrap()
+            .iter()
+            .map(|v| if let Value::Integer(n) = v { *n } else { panic!("se esperaba un Int: {:?}", v) })
+            .collect::<Vec<_>>();
+        numbers.sort();
+        numbers
+    }
+
+    fn seeded(seed: u64) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.seed_random(seed);
+        interpreter
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_sequence() {
+        let (mut a, mut b) = (seeded(42), seeded(42));
+        let first = (0..5).map(|_| draws(&mut a)).collect::<Vec<_>>();
+        assert_eq!(first, (0..5).map(|_| draws(&mut b)).collect::<Vec<_>>());
+        assert_ne!(first, (0..5).map(|_| draws(&mut seeded(43))).collect::<Vec<_>>());
+
+        // `random.seed` reinicia la secuencia igual que `--seed`.
+        let mut c = Interpreter::new();
+        seed(&mut c, vec![Value::Integer(42)]).unwrap();
+        assert_eq!(draws(&mut c), first[0]);
+    }
+
+    #[test]
+    fn a_script_can_replay_its_draws() {
+        let source = r#"
+fn draws() -> List<Any> {
+    return [random.int(1, 100), random.float(), random.choice(["a", "b", "c"]), random.shuffle([1, 2, 3, 4]), random.sample([1, 2, 3, 4], 2)]
+}
+fn main() -> int {
+    random.seed(7);
+    define first::List<Any> := draws();
+    random.seed(7);
+    if draws() != first { return 1 }
+    return 0
+}
+"#;
+        assert_eq!(run_source(source).unwrap(), 0);
+    }
+
+    #[test]
+    fn draws_stay_in_range() {
+        let mut interpreter = seeded(1);
+        for _ in 0..200 {
+            let Value::Integer(n) = int(&mut interpreter, vec![Value::Integer(-2), Value::Integer(2)]).unwrap() else {
+                panic!("random.int debe devolver un Int");
+            };
+            assert!((-2..=2).contains(&n));
+            let Value::Float(x) = float(&mut interpreter, vec![Value::Integer(1), Value::Integer(3)]).unwrap() else {
+                panic!("random.float debe devolver un Float");
+            };
+            assert!((1.0..3.0).contains(&x));
+        }
+        assert_eq!(int(&mut interpreter, vec![Value::Integer(5), Value::Integer(5)]).unwrap(), Value::Integer(5));
+
+        let shuffled = shuffle(&mut interpreter, vec![ints(&[3, 1, 2, 1])]).unwrap();
+        assert_eq!(sorted(shuffled), [1, 1, 2, 3]);
+        // Sin repetición: tomar todos los elementos es una permutación.
+        let picked = sample(&mut interpreter, vec![ints(&[1, 2, 3]), Value::Integer(3)]).unwrap();
+        assert_eq!(sorted(picked), [1, 2, 3]);
+    }
+
+    #[test]
+    fn impossible_draws_are_value_errors() {
+        let mut interpreter = seeded(1);
+        let errors = [
+            int(&mut interpreter, vec![Value::Integer(3), Value::Integer(2)]),
+            float(&mut interpreter, vec![Value::Float(1.0), Value::Float(0.5)]),
+            choice(&mut interpreter, vec![ints(&[])]),
+            sample(&mut interpreter, vec![ints(&[1, 2]), Value::Integer(3)]),
+            sample(&mut interpreter, vec![ints(&[1, 2]), Value::Integer(-1)]),
+        ];
+        for result in errors {
+            assert_eq!(result.unwrap_err().kind, ErrorKind::Value);
+        }
+    }
+}
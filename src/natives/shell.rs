@@ -0,0 +1,416 @@
+//! The `shell` module. `shell.run(command)` runs a `Text` command through
+//! the system shell (`sh -c`, or `cmd /C` on Windows) or a `List<Text>`
+//! argv directly, with no shell in between, so arguments are never
+//! reinterpreted. It waits for the process and returns a map:
+//!
+//! - `stdout`, `stderr`: the captured output as `Text`;
+//! - `status`: the exit code, or `void` if the process was killed;
+//! - `timed_out`: whether the `timeout` option stopped it.
+//!
+//! An optional second argument is a map of options: `cwd` (`Text`), `env`
+//! (`Map<Text, Text>`, added to the inherited environment), `stdin`
+//! (`Text` written to the process) and `timeout` (milliseconds).
+//!
+//! Running anything needs the `Run` capability. An argv list is checked
+//! against the granted programs (`--allow-run=rustc`), which are located
+//! when granted; the located file is what runs, whatever `cwd` or `env`
+//! say. A `Text` command goes through the shell, which could run anything,
+//! so it needs `Run` granted without restrictions, and so does overriding
+//! `PATH` or the variables that make the loader inject code (`LD_*`,
+//! `DYLD_*`).
+
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{arg_type_error, expect_args_between};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::Interpreter;
+use crate::ordered_map::OrderedMap;
+use crate::permissions::Capability;
+use crate::types::Type;
+
+/// How often a process with a timeout is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("shell.run", run);
+}
+
+#[derive(Default)]
+struct RunOptions {
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+    stdin: Option<String>,
+    timeout: Option<Duration>,
+}
+
+fn run(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("shell.run", &args, 1, 2)?;
+    let (mut command, shown) = build_command(interpreter, &args[0])?;
+    let options = match args.get(1) {
+        Some(Value::Map(map)) => parse_options(map)?,
+        Some(other) => return Err(arg_type_error("shell.run", 1, "'Map'", other)),
+        None => RunOptions::default(),
+    };
+    if !interpreter.permissions.allows_all(Capability::Run)
+        && let Some((name, _)) = options.env.iter().find(|(name, _)| chooses_program(name))
+    {
+        return Err(RuntimeError::new(
+            ErrorKind::Permission,
+            format!(
+                "'shell.run': cambiar la variable de entorno '{}' requiere {} sin restricciones",
+                name,
+                Capability::Run.flag()
+            ),
+        ));
+    }
+
+    if let Some(cwd) = &options.cwd {
+        command.current_dir(cwd);
+    }
+    command.envs(options.env.iter().map(|(k, v)| (k, v)));
+    command
+        .stdin(if options.stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| {
+        RuntimeError::new(ErrorKind::Io, format!("'shell.run': no se pudo ejecutar '{}': {}", shown, e))
+    })?;
+    let finished = wait(&mut child, options.stdin, options.timeout)
+        .map_err(|e| RuntimeError::new(ErrorKind::Io, format!("'shell.run': '{}': {}", shown, e)))?;
+
+    let mut result = OrderedMap::new();
+    result.insert("stdout".to_string(), Value::String(finished.stdout));
+    result.insert("stderr".to_string(), Value::String(finished.stderr));
+    result.insert("status".to_string(), finished.status.map_or(Value::Void, |code| Value::Integer(code as i64)));
+    result.insert("timed_out".to_string(), Value::Boolean(finished.timed_out));
+    Ok(Value::Map(result))
+}
+
+/// Variables that decide which program or libraries actually run.
+fn chooses_program(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    name == "PATH" || name.starts_with("LD_") || name.starts_with("DYLD_")
+}
+
+/// The command to spawn and how to name it in error messages, once the
+/// permissions allow it.
+fn build_command(interpreter: &Interpreter, value: &Value) -> Result<(Command, String), RuntimeError> {
+    match value {
+        Value::String(line) => {
+            if !interpreter.permissions.allows_all(Capability::Run) {
+                return Err(RuntimeError::new(
+                    ErrorKind::Permission,
+                    format!(
+                        "'shell.run': ejecutar '{}' con la shell requiere {} sin restricciones; \
+                         usa una lista de argumentos para permitir solo ciertos programas",
+                        line,
+                        Capability::Run.flag()
+                    ),
+                ));
+            }
+            #[cfg(target_os = "windows")]
+            let command = {
+                let mut command = Command::new("cmd");
+                command.args(["/C", line]);
+                command
+            };
+            #[cfg(not(target_os = "windows"))]
+            let command = {
+                let mut command = Command::new("sh");
+                command.args(["-c", line]);
+                command
+            };
+            Ok((command, line.clone()))
+        }
+        Value::List(items) => {
+            let argv = items
+                .iter()
+                .map(|item| match item {
+                    Value::String(arg) => Ok(arg.clone()),
+                    _ => Err(arg_type_error("shell.run", 0, "'Text' o 'List<Text>'", value)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let Some((program, rest)) = argv.split_first() else {
+                return Err(RuntimeError::new(ErrorKind::Value, "'shell.run': la lista de argumentos está vacía"));
+            };
+            let program = interpreter.permissions.check_program("shell.run", program)?;
+            let mut command = Command::new(program);
+            command.args(rest);
+            Ok((command, argv.join(" ")))
+        }
+        other => Err(arg_type_error("shell.run", 0, "'Text' o 'List<Text>'", other)),
+    }
+}
+
+fn parse_options(map: &OrderedMap<Value>) -> Result<RunOptions, RuntimeError> {
+    let option_error = |key: &str, expected: &str, value: &Value| {
+        RuntimeError::type_error(format!(
+            "'shell.run': la opción '{}' espera {} pero recibió '{}'",
+            key,
+            expected,
+            Type::of(value)
+        ))
+    };
+    let mut options = RunOptions::default();
+    for (key, value) in map.iter() {
+        match (key.as_str(), value) {
+            ("cwd", Value::String(dir)) => options.cwd = Some(dir.clone()),
+            ("stdin", Value::String(input)) => options.stdin = Some(input.clone()),
+            ("timeout", Value::Integer(ms)) if *ms >= 0 => options.timeout = Some(Duration::from_millis(*ms as u64)),
+            ("timeout", Value::Integer(ms)) => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Value,
+                    format!("'shell.run': el tiempo límite no puede ser negativo ({})", ms),
+                ));
+            }
+            ("env", Value::Map(vars)) => {
+                for (name, var) in vars.iter() {
+                    let Value::String(var) = var else {
+                        return Err(option_error("env", "valores 'Text'", var));
+                    };
+                    options.env.push((name.clone(), var.clone()));
+                }
+            }
+            ("cwd" | "stdin", other) => return Err(option_error(key, "'Text'", other)),
+            ("timeout", other) => return Err(option_error(key, "'Int'", other)),
+            ("env", other) => return Err(option_error(key, "'Map<Text, Text>'", other)),
+            _ => {
+                return Err(RuntimeError::new(
+                    ErrorKind::Value,
+                    format!("'shell.run': opción desconocida '{}'", key),
+                ));
+            }
+        }
+    }
+    Ok(options)
+}
+
+struct Finished {
+    stdout: String,
+    stderr: String,
+    status: Option<i32>,
+    timed_out: bool,
+}
+
+/// Feeds `stdin`, drains both pipes on their own threads (so a chatty
+/// process cannot fill a pipe and block) and waits, killing the process
+/// once `timeout` has passed.
+fn wait(child: &mut Child, stdin: Option<String>, timeout: Option<Duration>) -> std::io::Result<Finished> {
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // Se escribe en otro hilo; cerrar la tubería al terminar envía EOF.
+        thread::spawn(move || pipe.write_all(input.as_bytes()));
+    }
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let mut timed_out = false;
+    let status = match timeout {
+        None => child.wait()?,
+        Some(limit) => {
+            let deadline = Instant::now() + limit;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    timed_out = true;
+                    // Puede haber terminado justo ahora; el error no importa.
+                    let _ = child.kill();
+                    break child.wait()?;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    };
+
+    // Tras un tiempo límite, un nieto del proceso (p. ej. bajo `sh -c`)
+    // puede seguir con las tuberías abiertas: se toma lo leído hasta ahora.
+    let (stdout, stderr) = if timed_out {
+        (stdout.snapshot(), stderr.snapshot())
+    } else {
+        (stdout.finish(), stderr.finish())
+    };
+    Ok(Finished { stdout, stderr, status: if timed_out { None } else { status.code() }, timed_out })
+}
+
+/// A pipe being read to the end on a background thread.
+struct Drain {
+    bytes: Arc<Mutex<Vec<u8>>>,
+    reader: thread::JoinHandle<()>,
+}
+
+impl Drain {
+    /// Waits for the pipe to close and returns everything read.
+    fn finish(self) -> String {
+        let Drain { bytes, reader } = self;
+        let _ = reader.join();
+        Drain::text(&bytes)
+    }
+
+    /// What has been read so far, without waiting.
+    fn snapshot(&self) -> String {
+        Drain::text(&self.bytes)
+    }
+
+    fn text(bytes: &Mutex<Vec<u8>>) -> String {
+        let bytes = bytes.lock().unwrap_or_else(|e| e.into_inner());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> Drain {
+    let bytes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&bytes);
+    let reader = thread::spawn(move || {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            sink.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(&chunk[..n]);
+        }
+    });
+    Drain { bytes, reader }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::permissions::Permissions;
+    use crate::test_support::text;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    /// A fresh directory under `target/`, named relative to the current
+    /// directory so that grants made from it are relative too.
+    fn scratch(name: &str) -> String {
+        let dir = format!("target/lunaria-shell-{}-{}", name, std::process::id());
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// An executable shell script at `path` that prints `message`.
+    fn script(path: &str, message: &str) {
+        let path = Path::new(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("#!/bin/sh\necho {}\n", message)).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn allowed(programs: &[&str]) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        for program in programs {
+            interpreter.permissions.grant(Capability::Run, Some(program));
+        }
+        interpreter
+    }
+
+    fn argv(items: &[&str]) -> Value {
+        Value::List(items.iter().map(|item| text(item)).collect())
+    }
+
+    fn options(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    fn field(result: &Value, key: &str) -> Value {
+        let Value::Map(map) = result else { panic!("{:?}", result) };
+        map.get(key).cloned().unwrap()
+    }
+
+    #[test]
+    fn only_granted_programs_run() {
+        let mut interpreter = allowed(&["sh"]);
+        let result = run(&mut interpreter, vec![argv(&["sh", "-c", "echo hola"])]).unwrap();
+        assert_eq!(field(&result, "stdout"), text("hola\n"));
+        assert_eq!(field(&result, "status"), Value::Integer(0));
+        let err = run(&mut interpreter, vec![argv(&["cat"])]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Permission);
+        let err = run(&mut interpreter, vec![text("echo hola")]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Permission);
+    }
+
+    #[test]
+    fn env_cannot_change_which_program_runs() {
+        let dir = scratch("path");
+        script(&format!("{}/sh", dir), "evil");
+        let absolute = fs::canonicalize(&dir).unwrap().to_string_lossy().into_owned();
+        let mut interpreter = allowed(&["sh"]);
+        for name in ["PATH", "Path", "LD_PRELOAD", "LD_LIBRARY_PATH", "DYLD_INSERT_LIBRARIES"] {
+            let env = options(vec![(name, text(&absolute))]);
+            let err = run(&mut interpreter, vec![argv(&["sh", "-c", "echo ok"]), options(vec![("env", env)])]);
+            assert_eq!(err.unwrap_err().kind, ErrorKind::Permission, "{}", name);
+        }
+
+        interpreter.permissions = Permissions::all();
+        let env = options(vec![("PATH", text(&absolute))]);
+        let result = run(&mut interpreter, vec![argv(&["/bin/sh", "-c", "echo $PATH"]), options(vec![("env", env)])]);
+        assert_eq!(field(&result.unwrap(), "stdout"), text(&format!("{}\n", absolute)));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn cwd_cannot_change_which_program_runs() {
+        let dir = scratch("cwd");
+        let program = format!("{}/prog", dir);
+        script(&program, "granted");
+        script(&format!("{}/evil/{}", dir, program), "evil");
+        let mut interpreter = allowed(&[&program]);
+
+        let cwd = options(vec![("cwd", text(&format!("{}/evil", dir)))]);
+        let result = run(&mut interpreter, vec![argv(&[&program]), cwd]).unwrap();
+        assert_eq!(field(&result, "stdout"), text("granted\n"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn options_reach_the_process() {
+        let dir = scratch("options");
+        let absolute = fs::canonicalize(&dir).unwrap().to_string_lossy().into_owned();
+        let mut interpreter = allowed(&["sh", "cat"]);
+
+        let result = run(&mut interpreter, vec![argv(&["cat"]), options(vec![("stdin", text("línea\n"))])]);
+        assert_eq!(field(&result.unwrap(), "stdout"), text("línea\n"));
+
+        let env = options(vec![("LUNARIA_VAR", text("luna"))]);
+        let result = run(&mut interpreter, vec![argv(&["sh", "-c", "echo $LUNARIA_VAR"]), options(vec![("env", env)])]);
+        assert_eq!(field(&result.unwrap(), "stdout"), text("luna\n"));
+
+        let result = run(&mut interpreter, vec![argv(&["sh", "-c", "pwd -P"]), options(vec![("cwd", text(&dir))])]);
+        assert_eq!(field(&result.unwrap(), "stdout"), text(&format!("{}\n", absolute)));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_timeout_kills_the_process() {
+        let mut interpreter = allowed(&["sh"]);
+        let started = Instant::now();
+        let limit = options(vec![("timeout", Value::Integer(300))]);
+        let result = run(&mut interpreter, vec![argv(&["sh", "-c", "echo antes; sleep 5"]), limit]).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert_eq!(field(&result, "timed_out"), Value::Boolean(true));
+        assert_eq!(field(&result, "status"), Value::Void);
+        assert_eq!(field(&result, "stdout"), text("antes\n"));
+    }
+
+    #[test]
+    fn bad_options_are_rejected() {
+        let mut interpreter = allowed(&["sh"]);
+        let mut attempt = |entries| run(&mut interpreter, vec![argv(&["sh"]), options(entries)]).unwrap_err().kind;
+        assert_eq!(attempt(vec![("timeout", Value::Integer(-1))]), ErrorKind::Value);
+        assert_eq!(attempt(vec![("colour", Value::Integer(1))]), ErrorKind::Value);
+        assert_eq!(attempt(vec![("cwd", Value::Integer(1))]), ErrorKind::Type);
+        assert_eq!(attempt(vec![("env", options(vec![("A", Value::Integer(1))]))]), ErrorKind::Type);
+    }
+}
@@ -0,0 +1,207 @@
+//! The `strings` module. Positions and lengths count characters, not
+//! bytes, so `strings.len("ñandú")` is 5. Every native returns a new string.
+//! `repeat` and the `pad_*` functions refuse to build a string larger than
+//! `MAX_TEXT_BYTES`.
+
+use super::{expect_args, expect_args_between, expect_int, expect_list, expect_text, value_error};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::{slice, stringify, Interpreter};
+
+/// Largest string (in UTF-8 bytes) that `repeat` and `pad_*` will build.
+const MAX_TEXT_BYTES: usize = 256 * 1024 * 1024;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("strings.len", |_, args| {
+        Ok(Value::Integer(text_arg("strings.len", &args)?.chars().count() as i64))
+    });
+    interpreter.define_native("strings.upper", |_, args| {
+        Ok(Value::String(text_arg("strings.upper", &args)?.to_uppercase()))
+    });
+    interpreter.define_native("strings.lower", |_, args| {
+        Ok(Value::String(text_arg("strings.lower", &args)?.to_lowercase()))
+    });
+    interpreter.define_native("strings.trim", |_, args| {
+        Ok(Value::String(text_arg("strings.trim", &args)?.trim().to_string()))
+    });
+    interpreter.define_native("strings.split", |_, args| split(args));
+    interpreter.define_native("strings.join", |_, args| join(args));
+    interpreter.define_native("strings.replace", |_, args| replace(args));
+    interpreter.define_native("strings.contains", |_, args| {
+        let (text, part) = text_pair("strings.contains", &args)?;
+        Ok(Value::Boolean(text.contains(&part)))
+    });
+    interpreter.define_native("strings.starts_with", |_, args| {
+        let (text, part) = text_pair("strings.starts_with", &args)?;
+        Ok(Value::Boolean(text.starts_with(&part)))
+    });
+    interpreter.define_native("strings.ends_with", |_, args| {
+        let (text, part) = text_pair("strings.ends_with", &args)?;
+        Ok(Value::Boolean(text.ends_with(&part)))
+    });
+    interpreter.define_native("strings.find", |_, args| find(args));
+    interpreter.define_native("strings.substring", |_, args| substring(args));
+    interpreter.define_native("strings.repeat", |_, args| repeat(args));
+    interpreter.define_native("strings.pad_left", |_, args| pad("strings.pad_left", args));
+    interpreter.define_native("strings.pad_right", |_, args| pad("strings.pad_right", args));
+    interpreter.define_native("strings.to_int", |_, args| to_int(args));
+    interpreter.define_native("strings.to_float", |_, args| to_float(args));
+}
+
+/// The single `Text` argument of `name`.
+fn text_arg(name: &str, args: &[Value]) -> Result<String, RuntimeError> {
+    expect_args(name, args, 1)?;
+    expect_text(name, args, 0)
+}
+
+fn text_pair(name: &str, args: &[Value]) -> Result<(String, String), RuntimeError> {
+    expect_args(name, args, 2)?;
+    Ok((expect_text(name, args, 0)?, expect_text(name, args, 1)?))
+}
+
+/// `strings.split(text)` splits on runs of whitespace; `strings.split(text,
+/// sep)` on every `sep`, and an empty `sep` yields the characters.
+fn split(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("strings.split", &args, 1, 2)?;
+    let text = expect_text("strings.split", &args, 0)?;
+    let parts: Vec<String> = if args.len() == 1 {
+        text.split_whitespace().map(str::to_string).collect()
+    } else {
+        let sep = expect_text("strings.split", &args, 1)?;
+        if sep.is_empty() {
+            text.chars().map(String::from).collect()
+        } else {
+            text.split(sep.as_str()).map(str::to_string).collect()
+        }
+    };
+    Ok(Value::List(parts.into_iter().map(Value::String).collect()))
+}
+
+/// `strings.join(list, sep)`; elements are formatted like `console.out`.
+fn join(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("strings.join", &args, 2)?;
+    let items = expect_list("strings.join", &args, 0)?;
+    let sep = expect_text("strings.join", &args, 1)?;
+    Ok(Value::String(items.iter().map(stringify).collect::<Vec<_>>().join(&sep)))
+}
+
+fn replace(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("strings.replace", &args, 3)?;
+    let text = expect_text("strings.replace", &args, 0)?;
+    let from = expect_text("strings.replace", &args, 1)?;
+    let to = expect_text("strings.replace", &args, 2)?;
+    if from.is_empty() {
+        return Err(value_error("'strings.replace': el texto a buscar no puede estar vacío".to_string()));
+    }
+    Ok(Value::String(text.replace(&from, &to)))
+}
+
+/// Character index of the first occurrence of `part`, or -1.
+fn find(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (text, part) = text_pair("strings.find", &args)?;
+    let index = text.find(&part).map_or(-1, |byte| text[..byte].chars().count() as i64);
+    Ok(Value::Integer(index))
+}
+
+/// `strings.substring(text, start, end)` by character index, with the same
+/// rules as `text[start:end]`: negative indices count from the end and
+/// out-of-range bounds are clamped. `end` defaults to the length.
+fn substring(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("strings.substring", &args, 2, 3)?;
+    let text = expect_text("strings.substring", &args, 0)?;
+    let start = expect_int("strings.substring", &args, 1)?;
+    let end = match args.get(2) {
+        Some(_) => Some(Value::Integer(expect_int("strings.substring", &args, 2)?)),
+        None => None,
+    };
+    slice(Value::String(text), Some(Value::Integer(start)), end)
+}
+
+/// Fails unless a result of `bytes` bytes (`None` if even that overflowed)
+/// stays within `MAX_TEXT_BYTES`.
+fn check_size(name: &str, bytes: Option<usize>) -> Result<(), RuntimeError> {
+    match bytes {
+        Some(bytes) if bytes <= MAX_TEXT_BYTES => Ok(()),
+        _ => Err(value_error(format!(
+            "'{}': el resultado superaría el tamaño máximo de texto ({} bytes)",
+            name, MAX_TEXT_BYTES
+        ))),
+    }
+}
+
+fn repeat(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("strings.repeat", &args, 2)?;
+    let text = expect_text("strings.repeat", &args, 0)?;
+    let times = expect_int("strings.repeat", &args, 1)?;
+    if times < 0 {
+        return Err(value_error(format!("'strings.repeat': no se puede repetir {} veces", times)));
+    }
+    check_size("strings.repeat", usize::try_from(times).ok().and_then(|n| text.len().checked_mul(n)))?;
+    Ok(Value::String(text.repeat(times as usize)))
+}
+
+/// `strings.pad_left(text, width, fill)` / `pad_right`: pads with `fill`
+/// (a single character, a space by default) up to `width` characters.
+fn pad(name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between(name, &args, 2, 3)?;
+    let text = expect_text(name, &args, 0)?;
+    let width = expect_int(name, &args, 1)?;
+    let fill = match args.get(2) {
+        Some(_) => expect_text(name, &args, 2)?,
+        None => " ".to_string(),
+    };
+    let mut fill_chars = fill.chars();
+    let (Some(fill), None) = (fill_chars.next(), fill_chars.next()) else {
+        return Err(value_error(format!("'{}': el relleno debe ser un solo carácter, no '{}'", name, fill)));
+    };
+    let missing = usize::try_from(width.max(0)).unwrap_or(usize::MAX).saturating_sub(text.chars().count());
+    check_size(name, missing.checked_mul(fill.len_utf8()).and_then(|bytes| bytes.checked_add(text.len())))?;
+    let padding = fill.to_string().repeat(missing);
+    Ok(Value::String(if name == "strings.pad_left" { padding + &text } else { text + &padding }))
+}
+
+fn to_int(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = text_arg("strings.to_int", &args)?;
+    text.trim().parse::<i64>().map(Value::Integer).map_err(|e| {
+        use std::num::IntErrorKind;
+        match e.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => RuntimeError::new(
+                ErrorKind::Overflow,
+                format!("'{}' no cabe en 'Int' (usa bigint() para enteros sin límite)", text),
+            ),
+            _ => value_error(format!("'{}' no es un entero válido", text)),
+        }
+    })
+}
+
+fn to_float(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = text_arg("strings.to_float", &args)?;
+    text.trim()
+        .parse::<f64>()
+        .map(Value::Float)
+        .map_err(|_| value_error(format!("'{}' no es un número válido", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::text;
+
+    #[test]
+    fn huge_repeats_are_value_errors() {
+        let err = repeat(vec![text("ab"), Value::Integer(i64::MAX)]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Value);
+        let err = repeat(vec![text("ab"), Value::Integer(MAX_TEXT_BYTES as i64)]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Value);
+        assert_eq!(repeat(vec![text("ab"), Value::Integer(3)]).unwrap(), text("ababab"));
+    }
+
+    #[test]
+    fn huge_padding_is_a_value_error() {
+        let err = pad("strings.pad_left", vec![text("x"), Value::Integer(i64::MAX)]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Value);
+        let err = pad("strings.pad_right", vec![text("x"), Value::Integer(200_000_000), text("ñ")]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Value);
+        assert_eq!(pad("strings.pad_left", vec![text("x"), Value::Integer(3), text("*")]).unwrap(), text("**x"));
+    }
+}
@@ -0,0 +1,501 @@
+//! The `time` module. Timestamps and durations are plain `Int`s counting
+//! milliseconds (timestamps since 1970-01-01T00:00:00Z), so durations are
+//! added and subtracted with the usual operators:
+//! `deadline := time.now() + time.minutes(5)`. Dates are always UTC.
+//!
+//! `time.format` and `time.parse` use ISO 8601 by default or a pattern with
+//! `%Y` (year), `%m`, `%d`, `%H`, `%M`, `%S` (two digits each), `%f`
+//! (milliseconds, three digits) and `%%`.
+
+use super::{arg_type_error, expect_args, expect_args_between, expect_int, expect_text, float_to_int, value_error};
+use crate::ast::Value;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::interpreter::{overflow_error, Interpreter};
+use crate::ordered_map::OrderedMap;
+
+const MS_PER_SECOND: i64 = 1000;
+const MS_PER_MINUTE: i64 = 60 * MS_PER_SECOND;
+const MS_PER_HOUR: i64 = 60 * MS_PER_MINUTE;
+const MS_PER_DAY: i64 = 24 * MS_PER_HOUR;
+
+pub fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("time.now", |interpreter, args| {
+        expect_args("time.now", &args, 0)?;
+        Ok(Value::Integer(interpreter.clock.now_ms()))
+    });
+    interpreter.define_native("time.monotonic", |interpreter, args| {
+        expect_args("time.monotonic", &args, 0)?;
+        Ok(Value::Integer(interpreter.clock.monotonic_ms()))
+    });
+    interpreter.define_native("time.sleep", sleep);
+
+    interpreter.define_native("time.ms", |_, args| duration("time.ms", args, 1));
+    interpreter.define_native("time.seconds", |_, args| duration("time.seconds", args, MS_PER_SECOND));
+    interpreter.define_native("time.minutes", |_, args| duration("time.minutes", args, MS_PER_MINUTE));
+    interpreter.define_native("time.hours", |_, args| duration("time.hours", args, MS_PER_HOUR));
+    interpreter.define_native("time.days", |_, args| duration("time.days", args, MS_PER_DAY));
+    interpreter.define_native("time.format_duration", |_, args| format_duration(args));
+
+    interpreter.define_native("time.format", |_, args| format(args));
+    interpreter.define_native("time.parse", |_, args| parse(args));
+    interpreter.define_native("time.parts", |_, args| parts(args));
+}
+
+fn sleep(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("time.sleep", &args, 1)?;
+    let ms = expect_int("time.sleep", &args, 0)?;
+    let ms = u64::try_from(ms)
+        .map_err(|_| value_error(format!("'time.sleep': la duración no puede ser negativa ({})", ms)))?;
+    interpreter.clock.sleep(ms);
+    Ok(Value::Void)
+}
+
+/// `time.seconds(n)` and friends: `n` units in milliseconds. Fractions are
+/// allowed (`time.seconds(1.5)` is 1500) and rounded to the millisecond.
+fn duration(name: &str, args: Vec<Value>, unit: i64) -> Result<Value, RuntimeError> {
+    expect_args(name, &args, 1)?;
+    match &args[0] {
+        Value::Integer(n) => n
+            .checked_mul(unit)
+            .map(Value::Integer)
+            .ok_or_else(|| overflow_error(format!("{}({})", name, n))),
+        Value::Float(f) => float_to_int(name, (f * unit as f64).round()),
+        other => Err(arg_type_error(name, 0, "un número", other)),
+    }
+}
+
+/// A readable duration such as `1h 2m 3s 4ms` (`0ms` for zero).
+fn format_duration(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("time.format_duration", &args, 1)?;
+    let ms = expect_int("time.format_duration", &args, 0)?;
+    let mut rest = ms.unsigned_abs();
+    let mut pieces = Vec::new();
+    for (unit, suffix) in [(MS_PER_DAY, "d"), (MS_PER_HOUR, "h"), (MS_PER_MINUTE, "m"), (MS_PER_SECOND, "s"), (1, "ms")] {
+        let count = rest / unit as u64;
+        rest %= unit as u64;
+        if count > 0 {
+            pieces.push(format!("{}{}", count, suffix));
+        }
+    }
+    let text = if pieces.is_empty() { "0ms".to_string() } else { pieces.join(" ") };
+    Ok(Value::String(if ms < 0 { format!("-{}", text) } else { text }))
+}
+
+/// A timestamp broken into its UTC calendar fields.
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    millisecond: i64,
+}
+
+impl DateTime {
+    fn from_timestamp(ms: i64) -> Self {
+        let days = ms.div_euclid(MS_PER_DAY);
+        let mut rest = ms.rem_euclid(MS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let hour = rest / MS_PER_HOUR;
+        rest %= MS_PER_HOUR;
+        let minute = rest / MS_PER_MINUTE;
+        rest %= MS_PER_MINUTE;
+        DateTime { year, month, day, hour, minute, second: rest / MS_PER_SECOND, millisecond: rest % MS_PER_SECOND }
+    }
+
+    /// The timestamp, or `None` if a field is out of range or the result
+    /// does not fit in an `Int`.
+    fn to_timestamp(&self) -> Option<i64> {
+        let valid = (1..=12).contains(&self.month)
+            && (1..=days_in_month(self.year, self.month)).contains(&self.day)
+            && (0..24).contains(&self.hour)
+            && (0..60).contains(&self.minute)
+            && (0..60).contains(&self.second)
+            && (0..1000).contains(&self.millisecond);
+        if !valid {
+            return None;
+        }
+        days_from_civil(self.year, self.month, self.day)
+            .checked_mul(MS_PER_DAY)?
+            .checked_add(self.hour * MS_PER_HOUR + self.minute * MS_PER_MINUTE)?
+            .checked_add(self.second * MS_PER_SECOND + self.millisecond)
+    }
+
+    /// 0 for Monday through 6 for Sunday.
+    fn weekday(timestamp: i64) -> i64 {
+        // El 1 de enero de 1970 fue jueves.
+        (timestamp.div_euclid(MS_PER_DAY) + 3).rem_euclid(7)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversiones entre días desde 1970-01-01 y fechas del calendario
+// gregoriano proléptico, según los algoritmos de Howard Hinnant.
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+const ISO_PATTERN: &str = "%Y-%m-%dT%H:%M:%S.%fZ";
+
+/// `time.format(ts)` gives ISO 8601 (`2024-03-01T12:00:00.000Z`);
+/// `time.format(ts, pattern)` follows `pattern`.
+fn format(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("time.format", &args, 1, 2)?;
+    let date = DateTime::from_timestamp(expect_int("time.format", &args, 0)?);
+    let pattern = match args.get(1) {
+        Some(_) => expect_text("time.format", &args, 1)?,
+        None => ISO_PATTERN.to_string(),
+    };
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", date.year)),
+            Some('m') => out.push_str(&format!("{:02}", date.month)),
+            Some('d') => out.push_str(&format!("{:02}", date.day)),
+            Some('H') => out.push_str(&format!("{:02}", date.hour)),
+            Some('M') => out.push_str(&format!("{:02}", date.minute)),
+            Some('S') => out.push_str(&format!("{:02}", date.second)),
+            Some('f') => out.push_str(&format!("{:03}", date.millisecond)),
+            Some('%') => out.push('%'),
+            other => return Err(unknown_directive("time.format", &pattern, other)),
+        }
+    }
+    Ok(Value::String(out))
+}
+
+fn unknown_directive(name: &str, pattern: &str, directive: Option<char>) -> RuntimeError {
+    let shown = directive.map_or(String::new(), String::from);
+    value_error(format!("'{}': directiva desconocida '%{}' en el formato '{}'", name, shown, pattern))
+}
+
+/// `time.parse(text)` reads ISO 8601: a date, optionally followed by `T`
+/// (or a space) and a time with optional seconds and fraction, and an
+/// optional `Z` or `±hh:mm` offset (UTC if absent).
+/// `time.parse(text, pattern)` reads `text` laid out as `pattern`.
+fn parse(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args_between("time.parse", &args, 1, 2)?;
+    let text = expect_text("time.parse", &args, 0)?;
+    let parsed = match args.get(1) {
+        Some(_) => {
+            let pattern = expect_text("time.parse", &args, 1)?;
+            parse_pattern(&text, &pattern)?.ok_or_else(|| {
+                value_error(format!("'time.parse': '{}' no coincide con el formato '{}'", text, pattern))
+            })?
+        }
+        None => parse_iso(&text)
+            .ok_or_else(|| value_error(format!("'time.parse': '{}' no es una fecha ISO 8601 válida", text)))?,
+    };
+    Ok(Value::Integer(parsed))
+}
+
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Scanner { chars: text.chars().peekable() }
+    }
+
+    /// Exactly `count` ASCII digits.
+    fn digits(&mut self, count: usize) -> Option<i64> {
+        let mut value = 0;
+        for _ in 0..count {
+            let digit = self.chars.next()?.to_digit(10)?;
+            value = value * 10 + digit as i64;
+        }
+        Some(value)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.chars.next_if_eq(&expected).is_some()
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.eat(expected).then_some(())
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.chars.peek().is_none()
+    }
+}
+
+fn parse_iso(text: &str) -> Option<i64> {
+    let mut s = Scanner::new(text);
+    let mut date = DateTime { year: 0, month: 0, day: 0, hour: 0, minute: 0, second: 0, millisecond: 0 };
+    date.year = s.digits(4)?;
+    s.expect('-')?;
+    date.month = s.digits(2)?;
+    s.expect('-')?;
+    date.day = s.digits(2)?;
+
+    let mut offset_minutes = 0;
+    if s.eat('T') || s.eat(' ') {
+        date.hour = s.digits(2)?;
+        s.expect(':')?;
+        date.minute = s.digits(2)?;
+        if s.eat(':') {
+            date.second = s.digits(2)?;
+            if s.eat('.') || s.eat(',') {
+                // Se conservan los milisegundos; el resto de la fracción se trunca.
+                let mut scale = 100;
+                let mut any = false;
+                while let Some(digit) = s.chars.next_if(|c| c.is_ascii_digit()) {
+                    date.millisecond += digit.to_digit(10)? as i64 * scale;
+                    scale /= 10;
+                    any = true;
+                }
+                if !any {
+                    return None;
+                }
+            }
+        }
+        if !s.eat('Z') {
+            let sign = if s.eat('+') { 1 } else if s.eat('-') { -1 } else { 0 };
+            if sign != 0 {
+                let hours = s.digits(2)?;
+                s.eat(':');
+                let minutes = s.digits(2)?;
+                if hours >= 24 || minutes >= 60 {
+                    return None;
+                }
+                offset_minutes = sign * (hours * 60 + minutes);
+            }
+        }
+    }
+    if !s.is_done() {
+        return None;
+    }
+    date.to_timestamp()?.checked_sub(offset_minutes * MS_PER_MINUTE)
+}
+
+/// `Ok(None)` when `text` does not match `pattern`; `Err` when the
+/// pattern itself is invalid.
+fn parse_pattern(text: &str, pattern: &str) -> Result<Option<i64>, RuntimeError> {
+    let mut s = Scanner::new(text);
+    let mut date = DateTime { year: 1970, month: 1, day: 1, hour: 0, minute: 0, second: 0, millisecond: 0 };
+    let mut directives = pattern.chars();
+    while let Some(c) = directives.next() {
+        let field = match c {
+            '%' => match directives.next() {
+                Some('Y') => Some((&mut date.year, 4)),
+                Some('m') => Some((&mut date.month, 2)),
+                Some('d') => Some((&mut date.day, 2)),
+                Some('H') => Some((&mut date.hour, 2)),
+                Some('M') => Some((&mut date.minute, 2)),
+                Some('S') => Some((&mut date.second, 2)),
+                Some('f') => Some((&mut date.millisecond, 3)),
+                Some('%') => None,
+                other => return Err(unknown_directive("time.parse", pattern, other)),
+            },
+            _ => None,
+        };
+        match field {
+            Some((slot, width)) => match s.digits(width) {
+                Some(value) => *slot = value,
+                None => return Ok(None),
+            },
+            // Un literal (o `%%`) debe aparecer tal cual en el texto.
+            None if s.eat(c) => {}
+            None => return Ok(None),
+        }
+    }
+    if !s.is_done() {
+        return Ok(None);
+    }
+    Ok(date.to_timestamp())
+}
+
+/// `time.parts(ts)`: a map with `year`, `month`, `day`, `hour`, `minute`,
+/// `second`, `millisecond` and `weekday` (0 = Monday).
+fn parts(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_args("time.parts", &args, 1)?;
+    let timestamp = expect_int("time.parts", &args, 0)?;
+    let date = DateTime::from_timestamp(timestamp);
+    let mut map = OrderedMap::new();
+    for (key, value) in [
+        ("year", date.year),
+        ("month", date.month),
+        ("day", date.day),
+        ("hour", date.hour),
+        ("minute", date.minute),
+        ("second", date.second),
+        ("millisecond", date.millisecond),
+        ("weekday", DateTime::weekday(timestamp)),
+    ] {
+        map.insert(key.to_string(), Value::Integer(value));
+    }
+    Ok(Value::Map(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::test_support::{run_on, text};
+
+    fn format_iso(ms: i64) -> String {
+        match format(vec![Value::Integer(ms)]).unwrap() {
+            Value::String(s) => s,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    fn parse_iso_text(s: &str) -> Result<i64, RuntimeError> {
+        match parse(vec![text(s)])? {
+            Value::Integer(ms) => Ok(ms),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /// Runs `source` on an interpreter whose clock is frozen at `now_ms`.
+    fn run_at(now_ms: i64, source: &str) -> i32 {
+        let mut interpreter = Interpreter::new();
+        interpreter.clock = Clock::fake(now_ms);
+        run_on(interpreter, source).unwrap()
+    }
+
+    #[test]
+    fn a_fake_clock_only_moves_when_sleeping() {
+        // 2024-02-29T23:59:59.999Z: un segundo y medio después ya es marzo.
+        let source = r#"
+fn main() -> int {
+    define start::int := time.now();
+    if time.now() != start { return 1 }
+    if time.format(start) != "2024-02-29T23:59:59.999Z" { return 2 }
+    time.sleep(1500);
+    if time.now() - start != 1500 { return 3 }
+    if time.monotonic() != 1500 { return 4 }
+    if time.format(time.now()) != "2024-03-01T00:00:01.499Z" { return 5 }
+    return 0
+}
+"#;
+        assert_eq!(run_at(1_709_251_199_999, source), 0);
+    }
+
+    #[test]
+    fn february_29_exists_only_in_leap_years() {
+        for (year, leap) in [(2024, true), (2023, false), (2000, true), (1900, false), (1600, true), (-4, true)] {
+            assert_eq!(is_leap_year(year), leap, "{}", year);
+        }
+        assert!(parse_iso_text("2024-02-29").is_ok());
+        assert!(parse_iso_text("2000-02-29").is_ok());
+        assert_eq!(parse_iso_text("2023-02-29").unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(parse_iso_text("1900-02-29").unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(format_iso(parse_iso_text("2000-02-29T12:34:56.789Z").unwrap()), "2000-02-29T12:34:56.789Z");
+        assert_eq!(
+            parse_iso_text("2024-03-01").unwrap() - parse_iso_text("2024-02-28").unwrap(),
+            2 * MS_PER_DAY
+        );
+        assert_eq!(
+            parse_iso_text("2023-03-01").unwrap() - parse_iso_text("2023-02-28").unwrap(),
+            MS_PER_DAY
+        );
+    }
+
+    #[test]
+    fn dates_before_1970_are_negative_timestamps() {
+        assert_eq!(parse_iso_text("1969-12-31T23:59:59.999Z").unwrap(), -1);
+        assert_eq!(format_iso(-1), "1969-12-31T23:59:59.999Z");
+        assert_eq!(parse_iso_text("1900-01-01").unwrap(), -2_208_988_800_000);
+        assert_eq!(format_iso(-2_208_988_800_000), "1900-01-01T00:00:00.000Z");
+        assert_eq!(parse_iso_text("0001-01-01T00:00:00Z").unwrap(), -62_135_596_800_000);
+        assert_eq!(format_iso(-62_135_596_800_000), "0001-01-01T00:00:00.000Z");
+
+        let Value::Map(fields) = parts(vec![Value::Integer(-1)]).unwrap() else { panic!() };
+        assert_eq!(fields.get("year"), Some(&Value::Integer(1969)));
+        assert_eq!(fields.get("millisecond"), Some(&Value::Integer(999)));
+        // El 31 de diciembre de 1969 fue miércoles.
+        assert_eq!(fields.get("weekday"), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn offsets_and_fractions_are_applied() {
+        let midnight = 1_704_067_200_000;
+        assert_eq!(parse_iso_text("2024-01-01T00:00:00Z").unwrap(), midnight);
+        assert_eq!(parse_iso_text("2024-01-01T01:00:00+01:00").unwrap(), midnight);
+        assert_eq!(parse_iso_text("2023-12-31 19:30-0430").unwrap(), midnight);
+        assert_eq!(parse_iso_text("2024-01-01T00:00:00,1239").unwrap(), midnight + 123);
+    }
+
+    #[test]
+    fn malformed_dates_are_value_errors() {
+        for bad in [
+            "",
+            "2024",
+            "2024-1-01",
+            "2024/01/01",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-04-31",
+            "2024-01-01T24:00",
+            "2024-01-01T12:60",
+            "2024-01-01T12",
+            "2024-01-01T12:00:00.",
+            "2024-01-01T12:00Zjunk",
+            "2024-01-01T12:00+25:00",
+            " 2024-01-01",
+        ] {
+            assert_eq!(parse_iso_text(bad).unwrap_err().kind, ErrorKind::Value, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn patterns_read_and_write_the_same_layout() {
+        let pattern = text("%d/%m/%Y %H:%M:%S.%f %%");
+        let ms = parse(vec![text("01/02/2024 03:04:05.006 %"), pattern.clone()]).unwrap();
+        assert_eq!(format(vec![ms, pattern]).unwrap(), text("01/02/2024 03:04:05.006 %"));
+        assert_eq!(parse(vec![text("31/02/2024"), text("%d/%m/%Y")]).unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(parse(vec![text("2024"), text("%Q")]).unwrap_err().kind, ErrorKind::Value);
+        assert_eq!(format(vec![Value::Integer(0), text("%")]).unwrap_err().kind, ErrorKind::Value);
+    }
+
+    #[test]
+    fn durations_convert_and_format() {
+        assert_eq!(duration("time.seconds", vec![Value::Float(1.5)], MS_PER_SECOND).unwrap(), Value::Integer(1500));
+        assert_eq!(duration("time.days", vec![Value::Integer(-2)], MS_PER_DAY).unwrap(), Value::Integer(-172_800_000));
+        let err = duration("time.days", vec![Value::Integer(i64::MAX)], MS_PER_DAY).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Overflow);
+        let err = duration("time.days", vec![Value::Float(1e300)], MS_PER_DAY).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Overflow);
+        assert_eq!(format_duration(vec![Value::Integer(-90_061_001)]).unwrap(), text("-1d 1h 1m 1s 1ms"));
+        assert_eq!(format_duration(vec![Value::Integer(0)]).unwrap(), text("0ms"));
+    }
+}
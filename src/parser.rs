@@ -1,14 +1,56 @@
-use crate::lexer::Token;
-use crate::ast::Expr;
+use crate::lexer::{Span, StringPart, Token};
+use crate::ast::{Expr, TypeExpr};
 
 pub struct Parser {
     tokens: Vec<Token>,
     position: usize,
+    spans: Vec<Span>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, position: 0 }
+        Self { tokens, position: 0, spans: Vec::new() }
+    }
+
+    /// Attaches the source span of each token (see `Lexer::spans`) so that
+    /// call sites can be reported in stack traces and parse errors can
+    /// point at the offending token.
+    pub fn with_spans(mut self, spans: Vec<Span>) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Line of the most recently consumed token, or 0 when unknown.
+    fn line(&self) -> usize {
+        self.position
+            .checked_sub(1)
+            .and_then(|i| self.spans.get(i))
+            .map(|span| span.line)
+            .unwrap_or(0)
+    }
+
+    /// Comments mean nothing to the parser; dropping them (and their spans)
+    /// up front keeps them from being taken for stray tokens.
+    fn drop_comments(&mut self) {
+        let mut spans = std::mem::take(&mut self.spans).into_iter();
+        let tokens = std::mem::take(&mut self.tokens);
+        for token in tokens {
+            let span = spans.next();
+            if !matches!(token, Token::Comment(_)) {
+                self.tokens.push(token);
+                self.spans.extend(span);
+            }
+        }
+    }
+
+    /// An error for a token that cannot start an expression, with its
+    /// position when known.
+    fn unexpected(&self, found: String) -> String {
+        let message = format!("❌ Unexpected {}, expected an expression", found);
+        match self.spans.get(self.position) {
+            Some(span) => format!("{} (line {}, column {})", message, span.line, span.column),
+            None => message,
+        }
     }
 
     fn advance(&mut self) -> Option<&Token> {
@@ -42,18 +84,14 @@ impl Parser {
             return Err("❌ Expected '::' after identifier".to_string());
         }
     
-        let type_hint = if let Some(Token::Identifier(t)) = self.advance() {
-            Some(t.clone())
-        } else {
-            return Err("❌ Expected type after '::'".to_string());
-        };
+        let type_hint = Some(self.parse_type()?);
     
         if !matches!(self.advance(), Some(Token::Operator(op)) if op == ":=") {
             return Err("❌ Expected ':=' after type".to_string());
         }
     
         // 💡 Aquí permitimos parsear expresiones completas (incluyendo llamadas a funciones)
-        let value = self.parse_expression()?;
+        let value = self.parse_operand()?;
     
         if matches!(self.peek(), Some(Token::Symbol(s)) if s == ";") {
             self.advance();
@@ -68,15 +106,60 @@ impl Parser {
     }
     
 
+    /// Parses a type expression: `Name` or `Name<Arg, ...>`, nested as needed.
+    fn parse_type(&mut self) -> Result<TypeExpr, String> {
+        let name = match self.advance() {
+            Some(Token::Identifier(id)) => id.clone(),
+            other => return Err(format!("❌ Expected type name, found: {:?}", other)),
+        };
+
+        let mut args = Vec::new();
+        if matches!(self.peek(), Some(Token::Operator(op)) if op == "<") {
+            self.advance(); // consume '<'
+            loop {
+                args.push(self.parse_type()?);
+                match self.advance() {
+                    Some(Token::Symbol(s)) if s == "," => continue,
+                    Some(Token::Operator(op)) if op == ">" => break,
+                    other => {
+                        return Err(format!(
+                            "❌ Expected ',' or '>' in type arguments of '{}', found: {:?}",
+                            name, other
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(TypeExpr { name, args })
+    }
+
+    fn parse_type_alias(&mut self) -> Result<Expr, String> {
+        let name = if let Some(Token::Identifier(id)) = self.advance() {
+            id.clone()
+        } else {
+            return Err("❌ Expected type name after 'type'".to_string());
+        };
+
+        if !matches!(self.advance(), Some(Token::Operator(op)) if op == "=") {
+            return Err(format!("❌ Expected '=' after type name '{}'", name));
+        }
+
+        let target = self.parse_type()?;
+
+        if matches!(self.peek(), Some(Token::Symbol(s)) if s == ";") {
+            self.advance();
+        }
+
+        Ok(Expr::TypeAlias { name, target })
+    }
+
     pub fn parse(&mut self) -> Result<Vec<Expr>, String> {
+        self.drop_comments();
         let mut expressions = Vec::new();
 
         while self.position < self.tokens.len() {
             match self.tokens.get(self.position) {
-                Some(Token::Keyword(kw)) if kw == "fn" => {
-                    let func = self.parse_single_function()?;
-                    expressions.push(func);
-                }
                 Some(Token::Keyword(kw)) if kw == "summon" => {
                     self.advance();
                 
@@ -118,11 +201,7 @@ impl Parser {
                                     return Err("❌ Expected '::' after parameter name".to_string());
                                 }
                 
-                                let param_type = if let Some(Token::Identifier(typ)) = self.advance() {
-                                    typ.clone()
-                                } else {
-                                    return Err("❌ Expected parameter type".to_string());
-                                };
+                                let param_type = self.parse_type()?;
                 
                                 params.push((param_name, param_type));
                 
@@ -137,25 +216,9 @@ impl Parser {
                                 return Err("❌ Expected '->' after parameters".to_string());
                             }
                 
-                            let return_type = if let Some(Token::Identifier(rt)) = self.advance() {
-                                rt.clone()
-                            } else {
-                                return Err("❌ Expected return type".to_string());
-                            };
+                            let return_type = self.parse_type()?;
                 
-                            if !matches!(self.advance(), Some(Token::Symbol(s)) if s == "{") {
-                                return Err("❌ Expected '{{' to start function body".to_string());
-                            }
-                
-                            let mut func_body = Vec::new();
-                            while !matches!(self.peek(), Some(Token::Symbol(s)) if s == "}") {
-                                let expr = self.parse_expression()?;
-                                if !matches!(expr, Expr::Empty) {
-                                    func_body.push(expr);
-                                }
-                            }
-                
-                            self.advance(); // consume '}'
+                            let func_body = self.parse_function_body()?;
                 
                             body.push(Expr::FunctionDef {
                                 name: fname,
@@ -164,9 +227,9 @@ impl Parser {
                                 body: func_body,
                             });
                         } else {
-                            let expr = self.parse_expression()?;
+                            let expr = self.parse_statement()?;
                             if !matches!(expr, Expr::Empty) {
-                                println!("⚠️ Ignorado en módulo '{}': {:?}", name, expr);
+                                eprintln!("⚠️ Ignorado en módulo '{}': {:?}", name, expr);
                             }
                         }
                     }
@@ -193,15 +256,9 @@ impl Parser {
                     expressions.push(Expr::ModuleImport(module_name));
                 }
 
-                Some(Token::Keyword(kw)) if kw == "define" => {
-                    self.advance();
-                    let assignment = self.parse_assignment()?;
-                    expressions.push(assignment);
-                }
-
                 _ => {
-                    let expr = self.parse_expression()?;
-                    expressions.push(expr);
+                    let stmt = self.parse_statement()?;
+                    expressions.push(stmt);
                 }
             }
         }
@@ -209,20 +266,376 @@ impl Parser {
         Ok(expressions)
     }
 
-    fn parse_expression(&mut self) -> Result<Expr, String> {
-        match self.tokens.get(self.position) {
+    /// Parses one statement, as found at top level or inside a block.
+    fn parse_statement(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Keyword(kw)) if kw == "fn" && matches!(self.tokens.get(self.position + 1), Some(Token::Identifier(_))) => {
+                self.parse_single_function()
+            }
+
+            Some(Token::Keyword(kw)) if kw == "define" => {
+                self.advance();
+                self.parse_assignment()
+            }
+
+            Some(Token::Keyword(kw)) if kw == "type" => {
+                self.advance();
+                self.parse_type_alias()
+            }
+
             Some(Token::Keyword(kw)) if kw == "return" => {
                 self.advance(); // consume 'return'
 
-                if matches!(self.peek(), Some(Token::Symbol(s)) if s == ";") {
+                if self.peek_symbol(";") {
                     self.advance();
                     return Ok(Expr::Return(Box::new(Expr::Empty)));
                 }
+                if self.peek_symbol("}") || self.peek().is_none() {
+                    return Ok(Expr::Return(Box::new(Expr::Empty)));
+                }
 
-                let expr = self.parse_expression()?;
+                let expr = self.parse_operand()?;
+                self.skip_semicolon();
                 Ok(Expr::Return(Box::new(expr)))
             }
 
+            Some(Token::Keyword(kw)) if kw == "break" || kw == "continue" => {
+                let stmt = if kw == "break" { Expr::Break } else { Expr::Continue };
+                self.advance();
+                self.skip_semicolon();
+                Ok(stmt)
+            }
+
+            Some(Token::Operator(op)) if op == "if" => self.parse_if(),
+
+            // Un `;` suelto es una sentencia vacía.
+            Some(Token::Symbol(s)) if s == ";" => {
+                self.advance();
+                Ok(Expr::Empty)
+            }
+
+            Some(Token::Keyword(kw)) if kw == "try" => {
+                self.advance(); // consume 'try'
+                let body = self.parse_block("try")?;
+                if !matches!(self.advance(), Some(Token::Keyword(kw)) if kw == "catch") {
+                    return Err("❌ Expected 'catch' after 'try' block".to_string());
+                }
+                let error_var = match self.peek() {
+                    Some(Token::Identifier(id)) => {
+                        let id = id.clone();
+                        self.advance();
+                        Some(id)
+                    }
+                    _ => None,
+                };
+                let handler = self.parse_block("catch")?;
+                Ok(Expr::Try { body, error_var, handler })
+            }
+
+            Some(Token::Keyword(kw)) if kw == "while" => {
+                self.advance(); // consume 'while'
+                let condition = self.parse_operand()?;
+                let body = self.parse_block("while")?;
+                Ok(Expr::While { condition: Box::new(condition), body })
+            }
+
+            Some(Token::Keyword(kw)) if kw == "for" => {
+                self.advance(); // consume 'for'
+                let var = match self.advance() {
+                    Some(Token::Identifier(id)) => id.clone(),
+                    other => return Err(format!("❌ Expected loop variable after 'for', found: {:?}", other)),
+                };
+                if !matches!(self.advance(), Some(Token::Keyword(kw)) if kw == "in") {
+                    return Err(format!("❌ Expected 'in' after loop variable '{}'", var));
+                }
+                let iterable = self.parse_operand()?;
+                let body = self.parse_block("for")?;
+                Ok(Expr::For { var, iterable: Box::new(iterable), body })
+            }
+
+            _ => self.parse_expression(),
+        }
+    }
+
+    /// Parses `if cond { } elif cond { } else { }`; `else if` is accepted too.
+    fn parse_if(&mut self) -> Result<Expr, String> {
+        self.advance(); // consume 'if'
+        let mut branches = Vec::new();
+        let condition = self.parse_operand()?;
+        branches.push((condition, self.parse_block("if")?));
+
+        let mut else_branch = None;
+        loop {
+            match self.peek() {
+                Some(Token::Operator(op)) if op == "elif" => {
+                    self.advance();
+                    let condition = self.parse_operand()?;
+                    branches.push((condition, self.parse_block("elif")?));
+                }
+                Some(Token::Operator(op)) if op == "else" => {
+                    self.advance();
+                    if matches!(self.peek(), Some(Token::Operator(op)) if op == "if") {
+                        self.advance();
+                        let condition = self.parse_operand()?;
+                        branches.push((condition, self.parse_block("else if")?));
+                    } else {
+                        else_branch = Some(self.parse_block("else")?);
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Expr::If { branches, else_branch })
+    }
+
+    /// Parses `{ statement* }`.
+    fn parse_block(&mut self, context: &str) -> Result<Vec<Expr>, String> {
+        if !self.peek_symbol("{") {
+            return Err(format!("❌ Expected '{{' to start '{}' block, found: {:?}", context, self.peek()));
+        }
+        self.advance(); // consume '{'
+
+        let mut body = Vec::new();
+        while !self.peek_symbol("}") {
+            if self.peek().is_none() {
+                return Err(format!("❌ Unexpected end of '{}' block, expected '}}'", context));
+            }
+            let stmt = self.parse_statement()?;
+            if !matches!(stmt, Expr::Empty) {
+                body.push(stmt);
+            }
+        }
+
+        self.advance(); // consume '}'
+        Ok(body)
+    }
+
+    fn skip_semicolon(&mut self) {
+        if self.peek_symbol(";") {
+            self.advance();
+        }
+    }
+
+    /// Parses one piece of an interpolated string. Each `{...}` placeholder
+    /// must hold exactly one expression.
+    fn parse_string_part(part: StringPart) -> Result<Expr, String> {
+        match part {
+            StringPart::Text(text) => Ok(Expr::String(text)),
+            StringPart::Code { tokens, spans } => {
+                let mut parser = Parser::new(tokens).with_spans(spans);
+                parser.drop_comments();
+                let expr = parser.parse_expression()?;
+                match parser.peek() {
+                    None => Ok(expr),
+                    Some(token) => Err(format!("❌ Unexpected {:?} in string interpolation", token)),
+                }
+            }
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expr, String> {
+        let expr = self.parse_operand()?;
+
+        // Reasignación: `x := valor` o `xs[i] := valor`
+        if matches!(self.peek(), Some(Token::Operator(op)) if op == ":=") {
+            if !matches!(expr, Expr::Identifier(_) | Expr::Index { .. }) {
+                return Err(format!("❌ Cannot assign to {:?}", expr));
+            }
+            self.advance(); // consume ':='
+            let value = self.parse_operand()?;
+            if matches!(self.peek(), Some(Token::Symbol(s)) if s == ";") {
+                self.advance();
+            }
+            return Ok(Expr::Reassign {
+                target: Box::new(expr),
+                value: Box::new(value),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses a value-producing expression: operators, calls, literals.
+    /// Unlike `parse_expression`, this never yields `:=`.
+    fn parse_operand(&mut self) -> Result<Expr, String> {
+        self.parse_binary(1)
+    }
+
+    fn binary_precedence(op: &str) -> Option<u8> {
+        match op {
+            "or" => Some(1),
+            "and" => Some(2),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => Some(3),
+            "+" | "-" => Some(4),
+            "*" | "/" | "%" => Some(5),
+            _ => None,
+        }
+    }
+
+    /// Precedence climbing over left-associative binary operators.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(Token::Operator(op)) = self.peek() {
+            let Some(precedence) = Self::binary_precedence(op) else { break };
+            if precedence < min_precedence {
+                break;
+            }
+            let op = op.clone();
+            self.advance(); // consume operator
+            if self.peek().is_none() {
+                return Err(format!("❌ Expected expression after '{}'", op));
+            }
+            let right = self.parse_binary(precedence + 1)?;
+            left = Expr::Binary { op, left: Box::new(left), right: Box::new(right) };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Operator(op)) if op == "-" => {
+                self.advance();
+                let operand = self.parse_unary()?;
+                Ok(Expr::Unary { op: "-".to_string(), operand: Box::new(operand) })
+            }
+            // `not` binds looser than comparisons: `not a == b` is `not (a == b)`.
+            Some(Token::Operator(op)) if op == "not" => {
+                self.advance();
+                let operand = self.parse_binary(3)?;
+                Ok(Expr::Unary { op: "not".to_string(), operand: Box::new(operand) })
+            }
+            _ => self.parse_postfix(),
+        }
+    }
+
+    /// Parses a primary expression followed by any `[index]`, `[start:end]`
+    /// or `(args)` suffixes.
+    fn parse_postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            // Llamada sobre un valor: `make_adder(1)(2)`, `handlers["ok"](x)`
+            if self.peek_symbol("(") {
+                self.advance(); // consume '('
+                let line = self.line();
+                let args = self.parse_arguments("<expression>")?;
+                expr = Expr::Call { callee: Box::new(expr), args, line };
+                continue;
+            }
+            if !self.peek_symbol("[") {
+                break;
+            }
+            self.advance(); // consume '['
+
+            let start = if self.peek_symbol(":") {
+                None
+            } else {
+                Some(Box::new(self.parse_operand()?))
+            };
+
+            if self.peek_symbol(":") {
+                self.advance(); // consume ':'
+                let end = if self.peek_symbol("]") {
+                    None
+                } else {
+                    Some(Box::new(self.parse_operand()?))
+                };
+                self.expect_symbol("]", "after slice")?;
+                expr = Expr::Slice { target: Box::new(expr), start, end };
+            } else {
+                self.expect_symbol("]", "after index")?;
+                let index = start.ok_or("❌ Expected index between '[' and ']'")?;
+                expr = Expr::Index { target: Box::new(expr), index };
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.tokens.get(self.position) {
+            Some(Token::String(s)) => {
+                let expr = Expr::String(s.clone());
+                self.advance();
+                Ok(expr)
+            }
+
+            Some(Token::Interpolated(parts)) => {
+                let parts = parts.clone();
+                self.advance();
+                parts.into_iter().map(Self::parse_string_part).collect::<Result<_, _>>().map(Expr::Interpolated)
+            }
+
+            Some(Token::Integer(n)) => {
+                let expr = Expr::Integer(*n);
+                self.advance();
+                Ok(expr)
+            }
+
+            Some(Token::Float(f)) => {
+                let expr = Expr::Float(*f);
+                self.advance();
+                Ok(expr)
+            }
+
+            Some(Token::Boolean(b)) => {
+                let expr = Expr::Boolean(*b);
+                self.advance();
+                Ok(expr)
+            }
+
+            Some(Token::Keyword(kw)) if kw == "fn" => self.parse_lambda(),
+
+            Some(Token::Symbol(s)) if s == "(" => {
+                self.advance(); // consume '('
+                let expr = self.parse_operand()?;
+                self.expect_symbol(")", "to close parenthesised expression")?;
+                Ok(expr)
+            }
+
+            Some(Token::Symbol(s)) if s == "[" => {
+                self.advance(); // consume '['
+                let mut items = Vec::new();
+                while !self.peek_symbol("]") {
+                    if self.peek().is_none() {
+                        return Err("❌ Unterminated list literal, expected ']'".to_string());
+                    }
+                    items.push(self.parse_operand()?);
+                    if self.peek_symbol(",") {
+                        self.advance();
+                    } else if !self.peek_symbol("]") {
+                        return Err(format!("❌ Expected ',' or ']' in list literal, found: {:?}", self.peek()));
+                    }
+                }
+                self.advance(); // consume ']'
+                Ok(Expr::List(items))
+            }
+
+            Some(Token::Symbol(s)) if s == "{" => {
+                self.advance(); // consume '{'
+                let mut pairs = Vec::new();
+                while !self.peek_symbol("}") {
+                    if self.peek().is_none() {
+                        return Err("❌ Unterminated map literal, expected '}'".to_string());
+                    }
+                    let key = self.parse_operand()?;
+                    self.expect_symbol(":", "between map key and value")?;
+                    let value = self.parse_operand()?;
+                    pairs.push((key, value));
+                    if self.peek_symbol(",") {
+                        self.advance();
+                    } else if !self.peek_symbol("}") {
+                        return Err(format!("❌ Expected ',' or '}}' in map literal, found: {:?}", self.peek()));
+                    }
+                }
+                self.advance(); // consume '}'
+                Ok(Expr::MapLiteral(pairs))
+            }
+
             Some(Token::Identifier(first)) => {
                 let mut name = first.clone();
                 self.advance();
@@ -250,68 +663,72 @@ impl Parser {
                 }
 
                 if matches!(self.peek(), Some(Token::Symbol(s)) if s == "(") {
-                    self.advance();
-                    let mut args = Vec::new();
-                    while !matches!(self.peek(), Some(Token::Symbol(s)) if s == ")") {
-                        match self.peek() {
-                            Some(Token::String(s)) => {
-                                args.push(Expr::String(s.clone()));
-                                self.advance();
-                            }
-                            Some(Token::Identifier(id)) => {
-                                args.push(Expr::Identifier(id.clone()));
-                                self.advance();
-                            }
-                            Some(Token::Number(n)) => {
-                                args.push(Expr::Number(*n));
-                                self.advance();
-                            }
-                            Some(Token::Boolean(b)) => {
-                                args.push(Expr::Boolean(*b));
-                                self.advance();
-                            }
-                            _ => break,
-                        }
-
-                        if matches!(self.peek(), Some(Token::Symbol(s)) if s == ",") {
-                            self.advance();
-                        }
-                    }
-                    self.advance(); // consume ')'
-                   return Ok(Expr::FunctionCall { name, args })
-                
+                    self.advance(); // consume '('
+                    let line = self.line();
+                    let args = self.parse_arguments(&name)?;
+                    return Ok(Expr::FunctionCall { name, args, line });
                 }
-                // Comprobar si hay acceso con corchetes: agenda["Juan"]
-if matches!(self.peek(), Some(Token::Symbol(s)) if s == "[") {
-    self.advance(); // consume '['
 
-    let key_expr = match self.advance() {
-        Some(Token::String(s)) => Expr::String(s.clone()),
-        Some(Token::Identifier(id)) => Expr::Identifier(id.clone()),
-        other => return Err(format!("❌ Expected string or identifier as map key, found: {:?}", other)),
-    };
+                Ok(Expr::Identifier(name))
+            }
 
-    if !matches!(self.advance(), Some(Token::Symbol(s)) if s == "]") {
-        return Err("❌ Expected ']' after map key".to_string());
+            Some(Token::Operator(op)) if Self::binary_precedence(op).is_some() || matches!(op.as_str(), "=" | ":=" | "->") => {
+                Err(self.unexpected(format!("operator '{}'", op)))
+            }
+
+            Some(Token::Keyword(s) | Token::Operator(s) | Token::Symbol(s)) => Err(self.unexpected(format!("'{}'", s))),
+
+            Some(other) => Err(self.unexpected(format!("{:?}", other))),
+
+            None => Err("❌ Unexpected end of input, expected an expression".to_string()),
+        }
     }
 
-    return Ok(Expr::MapAccess {
-        map: Box::new(Expr::Identifier(name)),
-        key: Box::new(key_expr),
-    });
-}
-                else {
-                    Ok(Expr::Identifier(name))
+    /// Parses call arguments up to and including the closing ')'.
+    fn parse_arguments(&mut self, callee: &str) -> Result<Vec<Expr>, String> {
+        let mut args = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Symbol(s)) if s == ")" => {
+                    self.advance();
+                    return Ok(args);
+                }
+                Some(Token::Symbol(s)) if s == "," => {
+                    return Err(format!("❌ Expected argument before ',' in call to '{}'", callee));
                 }
+                None => return Err(format!("❌ Unterminated argument list in call to '{}', expected ')'", callee)),
+                _ => {}
             }
 
-            _ => {
-                self.advance();
-                Ok(Expr::Empty)
+            args.push(self.parse_operand()?);
+
+            match self.peek() {
+                Some(Token::Symbol(s)) if s == "," => {
+                    self.advance();
+                }
+                Some(Token::Symbol(s)) if s == ")" => {}
+                None => return Err(format!("❌ Unterminated argument list in call to '{}', expected ')'", callee)),
+                other => {
+                    return Err(format!(
+                        "❌ Expected ',' or ')' after argument {} in call to '{}', found: {:?}",
+                        args.len(), callee, other
+                    ));
+                }
             }
         }
     }
 
+    fn peek_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if s == symbol)
+    }
+
+    fn expect_symbol(&mut self, symbol: &str, context: &str) -> Result<(), String> {
+        match self.advance() {
+            Some(Token::Symbol(s)) if s == symbol => Ok(()),
+            other => Err(format!("❌ Expected '{}' {}, found: {:?}", symbol, context, other)),
+        }
+    }
+
     // ✅ Función auxiliar: parsea una única función sin recursión infinita
     fn parse_single_function(&mut self) -> Result<Expr, String> {
         self.advance(); // consume 'fn'
@@ -326,6 +743,45 @@ if matches!(self.peek(), Some(Token::Symbol(s)) if s == "[") {
             return Err("❌ Expected '(' after function name".to_string());
         }
 
+        let params = self.parse_params()?;
+
+        if !matches!(self.advance(), Some(Token::Operator(op)) if op == "->") {
+            return Err("❌ Expected '->' after parameters".to_string());
+        }
+
+        let return_type = self.parse_type()?;
+        let body = self.parse_function_body()?;
+
+        Ok(Expr::FunctionDef {
+            name,
+            params,
+            return_type,
+            body,
+        })
+    }
+
+    /// Parses an anonymous function: `fn(x::int) -> int { ... }`.
+    /// The return type may be omitted, in which case it is `Any`.
+    fn parse_lambda(&mut self) -> Result<Expr, String> {
+        self.advance(); // consume 'fn'
+        self.expect_symbol("(", "after 'fn' in anonymous function")?;
+
+        let params = self.parse_params()?;
+
+        let return_type = if matches!(self.peek(), Some(Token::Operator(op)) if op == "->") {
+            self.advance();
+            self.parse_type()?
+        } else {
+            TypeExpr::simple("Any")
+        };
+
+        let body = self.parse_function_body()?;
+
+        Ok(Expr::Lambda { params, return_type, body })
+    }
+
+    /// Parses `name::Type, ...` up to and including the closing ')'.
+    fn parse_params(&mut self) -> Result<Vec<(String, TypeExpr)>, String> {
         let mut params = Vec::new();
         while !matches!(self.peek(), Some(Token::Symbol(s)) if s == ")") {
             let param_name = if let Some(Token::Identifier(id)) = self.advance() {
@@ -338,11 +794,7 @@ if matches!(self.peek(), Some(Token::Symbol(s)) if s == "[") {
                 return Err("❌ Expected '::' after parameter name".to_string());
             }
 
-            let param_type = if let Some(Token::Identifier(typ)) = self.advance() {
-                typ.clone()
-            } else {
-                return Err("❌ Expected parameter type".to_string());
-            };
+            let param_type = self.parse_type()?;
 
             params.push((param_name, param_type));
 
@@ -352,37 +804,53 @@ if matches!(self.peek(), Some(Token::Symbol(s)) if s == "[") {
         }
 
         self.advance(); // consume ')'
+        Ok(params)
+    }
 
-        if !matches!(self.advance(), Some(Token::Operator(op)) if op == "->") {
-            return Err("❌ Expected '->' after parameters".to_string());
-        }
+    fn parse_function_body(&mut self) -> Result<Vec<Expr>, String> {
+        self.parse_block("function body")
+    }
+}
 
-        let return_type = if let Some(Token::Identifier(rt)) = self.advance() {
-            rt.clone()
-        } else {
-            return Err("❌ Expected return type".to_string());
-        };
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::ast::Expr;
+    use crate::lexer::Lexer;
 
-        if !matches!(self.advance(), Some(Token::Symbol(s)) if s == "{") {
-            return Err("❌ Expected '{' to start function body".to_string());
-        }
+    fn parse(source: &str) -> Result<Vec<Expr>, String> {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize().expect("el ejemplo debe ser léxicamente válido");
+        Parser::new(tokens).with_spans(lexer.spans).parse()
+    }
 
-        let mut body = Vec::new();
-        while !matches!(self.peek(), Some(Token::Symbol(s)) if s == "}") {
-            if self.peek().is_none() {
-                return Err("❌ Unexpected end of function body".to_string());
-            }
-            let expr = self.parse_expression()?;
-            body.push(expr);
+    #[test]
+    fn stray_tokens_are_errors_at_their_position() {
+        let cases = [
+            ("if a { } else { } else { }", "'else'", 1, 19),
+            ("define x::int := 1;\nelif b { }", "'elif'", 2, 1),
+            ("define x::int := match;", "'match'", 1, 18),
+            ("try { } catch e { }\n  catch f { }", "'catch'", 2, 3),
+            ("for x in xs { console.out(in) }", "'in'", 1, 27),
+            ("console.out(1, *);", "operator '*'", 1, 16),
+            ("x := ;", "';'", 1, 6),
+            ("define m::Map<Text, int> := { \"a\": . };", "'.'", 1, 36),
+            ("console.out(\"{else}\");", "'else'", 1, 15),
+        ];
+        for (source, found, line, column) in cases {
+            let message = format!("❌ Unexpected {}, expected an expression (line {}, column {})", found, line, column);
+            assert_eq!(parse(source).unwrap_err(), message, "{:?}", source);
         }
+        assert_eq!(parse("define x::int :=").unwrap_err(), "❌ Unexpected end of input, expected an expression");
+    }
 
-        self.advance(); // consume '}'
-
-        Ok(Expr::FunctionDef {
-            name,
-            params,
-            return_type,
-            body,
-        })
+    #[test]
+    fn comments_and_empty_statements_are_skipped() {
+        let source = "~> note\nconsole.out(1);;\nif a { ~< why >~ } ~> then\nelse { ; }\ndefine xs::List<int> := [1, ~> one\n 2];";
+        let program = parse(source).expect("el ejemplo debe ser sintácticamente válido");
+        let statements = program.iter().filter(|expr| !matches!(expr, Expr::Empty)).collect::<Vec<_>>();
+        assert_eq!(statements.len(), 3, "{:?}", program);
+        assert!(matches!(statements[1], Expr::If { branches, else_branch: Some(other) } if branches[0].1.is_empty() && other.is_empty()));
+        assert!(matches!(statements[2], Expr::Assignment { value, .. } if matches!(value.as_ref(), Expr::List(items) if items.len() == 2)));
     }
 }
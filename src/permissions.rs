@@ -0,0 +1,348 @@
+//! Capabilities a script needs before it can touch the outside world.
+//! Everything is denied by default; the CLI (`--allow-read=src`,
+//! `--allow-write=out/`, `--allow-run=rustc`, ...) or an embedder grants
+//! access through `Permissions::grant`, and natives call the `check_*`
+//! methods before acting.
+
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{ErrorKind, RuntimeError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    Read,
+    Write,
+    Run,
+    Env,
+    Net,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] =
+        [Capability::Read, Capability::Write, Capability::Run, Capability::Env, Capability::Net];
+
+    /// The capability controlled by a CLI flag such as `--allow-read`.
+    pub fn from_flag(flag: &str) -> Option<Capability> {
+        Capability::ALL.into_iter().find(|cap| cap.flag() == flag)
+    }
+
+    pub fn flag(self) -> &'static str {
+        match self {
+            Capability::Read => "--allow-read",
+            Capability::Write => "--allow-write",
+            Capability::Run => "--allow-run",
+            Capability::Env => "--allow-env",
+            Capability::Net => "--allow-net",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            Capability::Read => "leer",
+            Capability::Write => "escribir",
+            Capability::Run => "ejecutar",
+            Capability::Env => "leer la variable de entorno",
+            Capability::Net => "conectarse a",
+        };
+        write!(f, "{}", action)
+    }
+}
+
+/// What has been granted for one capability.
+#[derive(Debug, Clone, Default, PartialEq)]
+enum Grant {
+    #[default]
+    Denied,
+    All,
+    /// Paths and programs (already resolved), variable names or hosts.
+    Only(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    read: Grant,
+    write: Grant,
+    run: Grant,
+    env: Grant,
+    net: Grant,
+}
+
+impl Permissions {
+    /// Every capability, without restrictions.
+    pub fn all() -> Self {
+        let mut permissions = Permissions::default();
+        for cap in Capability::ALL {
+            permissions.grant(cap, None);
+        }
+        permissions
+    }
+
+    fn slot(&mut self, cap: Capability) -> &mut Grant {
+        match cap {
+            Capability::Read => &mut self.read,
+            Capability::Write => &mut self.write,
+            Capability::Run => &mut self.run,
+            Capability::Env => &mut self.env,
+            Capability::Net => &mut self.net,
+        }
+    }
+
+    /// Grants `cap` for one `target` (a path for `Read`/`Write`, a program
+    /// for `Run`, a variable for `Env`, a host for `Net`), or for everything
+    /// when `target` is `None`. Paths and programs are resolved (see
+    /// `resolve` and `locate`) at the time of the grant; one that cannot be
+    /// resolved grants nothing.
+    pub fn grant(&mut self, cap: Capability, target: Option<&str>) {
+        let slot = self.slot(cap);
+        let Some(target) = target else {
+            *slot = Grant::All;
+            return;
+        };
+        let target = match cap {
+            Capability::Read | Capability::Write => match resolve(target) {
+                Some(root) => root.to_string_lossy().into_owned(),
+                None => return,
+            },
+            Capability::Run => match locate(target) {
+                Some(program) => program.to_string_lossy().into_owned(),
+                None => return,
+            },
+            _ => target.to_string(),
+        };
+        match slot {
+            Grant::All => {}
+            Grant::Only(targets) => targets.push(target),
+            Grant::Denied => *slot = Grant::Only(vec![target]),
+        }
+    }
+
+    fn grant_for(&self, cap: Capability) -> &Grant {
+        match cap {
+            Capability::Read => &self.read,
+            Capability::Write => &self.write,
+            Capability::Run => &self.run,
+            Capability::Env => &self.env,
+            Capability::Net => &self.net,
+        }
+    }
+
+    /// Whether `cap` was granted without restrictions.
+    pub fn allows_all(&self, cap: Capability) -> bool {
+        *self.grant_for(cap) == Grant::All
+    }
+
+    /// Fails unless `path` is inside a directory (or is a file) granted
+    /// for `cap`. Both sides are compared after `resolve`, so a symbolic
+    /// link inside a granted directory cannot lead outside of it.
+    pub fn check_path(&self, name: &str, cap: Capability, path: &str) -> Result<(), RuntimeError> {
+        let allowed = match self.grant_for(cap) {
+            Grant::All => true,
+            Grant::Denied => false,
+            Grant::Only(roots) => match resolve(path) {
+                Some(path) => roots.iter().any(|root| path.starts_with(root)),
+                None => false,
+            },
+        };
+        if allowed { Ok(()) } else { Err(denied(name, cap, path)) }
+    }
+
+    /// Fails unless `program` is a program granted for `Run`, and returns
+    /// the file to spawn: the one located when the grant was made, so a
+    /// different `PATH` or working directory for the child cannot swap it
+    /// for another. Without restrictions `program` is returned as written.
+    pub fn check_program(&self, name: &str, program: &str) -> Result<PathBuf, RuntimeError> {
+        match &self.run {
+            Grant::All => Ok(PathBuf::from(program)),
+            Grant::Only(programs) => match locate(program) {
+                Some(path) if programs.iter().any(|granted| Path::new(granted) == path) => Ok(path),
+                _ => Err(denied(name, Capability::Run, program)),
+            },
+            Grant::Denied => Err(denied(name, Capability::Run, program)),
+        }
+    }
+
+    /// Fails unless `target` (a variable or host) was granted for `cap` by
+    /// exact name.
+    pub fn check(&self, name: &str, cap: Capability, target: &str) -> Result<(), RuntimeError> {
+        let allowed = match self.grant_for(cap) {
+            Grant::All => true,
+            Grant::Denied => false,
+            Grant::Only(targets) => targets.iter().any(|t| t == target),
+        };
+        if allowed { Ok(()) } else { Err(denied(name, cap, target)) }
+    }
+}
+
+fn denied(name: &str, cap: Capability, target: &str) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Permission,
+        format!("'{}': sin permiso para {} '{}' (usa {})", name, cap, target, cap.flag()),
+    )
+}
+
+/// Where `path` really points: made absolute against the current
+/// directory, following every symbolic link in the part that exists and
+/// resolving `.` and `..` as the operating system would. The part that does
+/// not exist yet (a file about to be written) is appended as written.
+/// `None` for a broken symbolic link, whose target cannot be checked.
+fn resolve(path: impl AsRef<Path>) -> Option<PathBuf> {
+    let joined = std::env::current_dir().unwrap_or_default().join(path);
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            // `resolved` no contiene enlaces, así que subir un nivel es exacto.
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => {
+                resolved.push(other);
+                match fs::canonicalize(&resolved) {
+                    Ok(real) => resolved = real,
+                    Err(_) if fs::symlink_metadata(&resolved).is_ok_and(|m| m.file_type().is_symlink()) => {
+                        return None;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+    Some(resolved)
+}
+
+/// Suffixes tried when looking a bare program name up in `PATH`.
+#[cfg(windows)]
+const PROGRAM_SUFFIXES: [&str; 2] = ["", ".exe"];
+#[cfg(not(windows))]
+const PROGRAM_SUFFIXES: [&str; 1] = [""];
+
+/// The file `program` runs: a path (anything with a separator) is taken
+/// from the current directory and may not exist yet (a program the script
+/// is about to build), while a bare name must be found in `PATH`. The
+/// directories are resolved as in `resolve` but the file name is kept,
+/// because tools such as `rustup` act on the name they are run under.
+fn locate(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    let found = if path.components().count() > 1 {
+        path.to_path_buf()
+    } else {
+        let dirs = std::env::var_os("PATH")?;
+        std::env::split_paths(&dirs).find_map(|dir| {
+            PROGRAM_SUFFIXES
+                .iter()
+                .map(|suffix| dir.join(format!("{}{}", program, suffix)))
+                .find(|file| is_program(file))
+        })?
+    };
+    Some(resolve(found.parent()?)?.join(found.file_name()?))
+}
+
+fn is_program(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lunaria-permissions-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
+
+    fn text(path: &std::path::Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn denies_everything_by_default() {
+        let permissions = Permissions::default();
+        let err = permissions.check_path("fs.read", Capability::Read, "README.md").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Permission);
+        assert!(permissions.check("shell.run", Capability::Run, "rustc").is_err());
+    }
+
+    #[test]
+    fn grants_paths_under_a_root_only() {
+        let dir = scratch("roots");
+        fs::create_dir(dir.join("out")).unwrap();
+        let mut permissions = Permissions::default();
+        permissions.grant(Capability::Write, Some(&text(&dir.join("out"))));
+
+        assert!(permissions.check_path("fs.write", Capability::Write, &text(&dir.join("out/new/file.txt"))).is_ok());
+        assert!(permissions.check_path("fs.write", Capability::Write, &text(&dir.join("outside.txt"))).is_err());
+        assert!(permissions.check_path("fs.write", Capability::Write, &text(&dir.join("out/../outside.txt"))).is_err());
+        assert!(permissions.check_path("fs.write", Capability::Write, &text(&dir.join("output.txt"))).is_err());
+        assert!(permissions.check_path("fs.read", Capability::Read, &text(&dir.join("out/file.txt"))).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_cannot_escape_a_root() {
+        let dir = scratch("symlinks");
+        fs::create_dir_all(dir.join("out/inner")).unwrap();
+        fs::create_dir(dir.join("secret")).unwrap();
+        std::os::unix::fs::symlink(dir.join("secret"), dir.join("out/esc")).unwrap();
+        std::os::unix::fs::symlink(dir.join("out/inner"), dir.join("out/ok")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("out/broken")).unwrap();
+        let mut permissions = Permissions::default();
+        permissions.grant(Capability::Write, Some(&text(&dir.join("out"))));
+
+        let check = |path: &str| permissions.check_path("fs.write", Capability::Write, &text(&dir.join(path)));
+        assert!(check("out/esc/pwned.txt").is_err());
+        assert!(check("out/esc").is_err());
+        assert!(check("out/broken").is_err());
+        assert!(check("out/ok/../../secret/x").is_err());
+        assert!(check("out/ok/file.txt").is_ok());
+        assert!(check("out/esc/../inner/file.txt").is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn programs_are_located_when_granted() {
+        let mut permissions = Permissions::default();
+        permissions.grant(Capability::Run, Some("sh"));
+        permissions.grant(Capability::Run, Some("no-such-program"));
+        permissions.grant(Capability::Run, Some("out/not-built-yet"));
+
+        let sh = permissions.check_program("shell.run", "sh").unwrap();
+        assert!(sh.is_absolute() && sh.ends_with("sh"), "{:?}", sh);
+        assert_eq!(permissions.check_program("shell.run", &text(&sh)).unwrap(), sh);
+        assert!(permissions.check_program("shell.run", "cat").is_err());
+        assert!(permissions.check_program("shell.run", "no-such-program").is_err());
+        let later = permissions.check_program("shell.run", "./out/not-built-yet").unwrap();
+        assert_eq!(later, std::env::current_dir().unwrap().join("out/not-built-yet"));
+        assert!(Permissions::default().check_program("shell.run", "sh").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symlinked_root_grants_its_target() {
+        let dir = scratch("linked-root");
+        fs::create_dir(dir.join("real")).unwrap();
+        std::os::unix::fs::symlink(dir.join("real"), dir.join("alias")).unwrap();
+        let mut permissions = Permissions::default();
+        permissions.grant(Capability::Read, Some(&text(&dir.join("alias"))));
+
+        assert!(permissions.check_path("fs.read", Capability::Read, &text(&dir.join("real/a.txt"))).is_ok());
+        assert!(permissions.check_path("fs.read", Capability::Read, &text(&dir.join("alias/a.txt"))).is_ok());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -24,12 +24,12 @@ pub fn start_repl(grim: Grimoire) {
         }
 
         // Soporte mínimo para: console.out("...")
-        if let Some(rest) = input.strip_prefix("console.out(") {
-            if let Some(arg) = rest.strip_suffix(");") {
-                let arg = arg.trim_matches('"');
-                grim.call_function("console", "out", vec![Value::String(arg.to_string())]);
-                continue;
-            }
+        if let Some(rest) = input.strip_prefix("console.out(")
+            && let Some(arg) = rest.strip_suffix(");")
+        {
+            let arg = arg.trim_matches('"');
+            grim.call_function("console", "out", vec![Value::String(arg.to_string())]);
+            continue;
         }
 
         println!("❌ Unknown or unsupported expression.");
@@ -0,0 +1,25 @@
+//! Helpers shared by the unit tests.
+
+use crate::ast::Value;
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+pub fn text(s: &str) -> Value {
+    Value::String(s.to_string())
+}
+
+/// Lexes, parses and runs `source` on `interpreter`, returning the exit
+/// code `main` gave.
+pub fn run_on(mut interpreter: Interpreter, source: &str) -> Result<i32, RuntimeError> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize().expect("el ejemplo debe ser léxicamente válido");
+    let ast = Parser::new(tokens).with_spans(lexer.spans).parse().expect("el ejemplo debe ser sintácticamente válido");
+    interpreter.run(ast)
+}
+
+/// `run_on` with a fresh interpreter.
+pub fn run_source(source: &str) -> Result<i32, RuntimeError> {
+    run_on(Interpreter::new(), source)
+}
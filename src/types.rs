@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::ast::{TypeExpr, Value};
+use crate::bigint::BigInt;
+
+/// Canonical Lunaria types. Every spelling accepted in a `::` annotation
+/// (`Text`, `string`, `i32`, `f64`, ...) resolves to one of these.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    BigInt,
+    Float,
+    Text,
+    Bool,
+    Void,
+    List(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Option(Box<Type>),
+    Function,
+    Any,
+}
+
+impl Type {
+    /// Returns the canonical type for a built-in spelling with no type
+    /// arguments. A bare `List` or `Map` holds `Any` elements.
+    pub fn builtin(name: &str) -> Option<Type> {
+        match name {
+            "int" | "Int" | "i32" | "i64" => Some(Type::Int),
+            "BigInt" | "bigint" => Some(Type::BigInt),
+            "float" | "Float" | "f32" | "f64" => Some(Type::Float),
+            "Text" | "string" | "String" | "str" => Some(Type::Text),
+            "bool" | "Bool" | "boolean" => Some(Type::Bool),
+            "void" | "Void" => Some(Type::Void),
+            "List" => Some(Type::List(Box::new(Type::Any))),
+            "Map" => Some(Type::Map(Box::new(Type::Text), Box::new(Type::Any))),
+            "Option" => Some(Type::Option(Box::new(Type::Any))),
+            "Fn" | "Function" => Some(Type::Function),
+            "any" | "Any" => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    /// The type a runtime value carries. Collections report their element
+    /// type when every element agrees, and `Any` otherwise.
+    pub fn of(value: &Value) -> Type {
+        match value {
+            Value::Integer(_) => Type::Int,
+            Value::BigInt(_) => Type::BigInt,
+            Value::Float(_) => Type::Float,
+            Value::String(_) => Type::Text,
+            Value::Boolean(_) => Type::Bool,
+            Value::Void => Type::Void,
+            Value::List(items) => Type::List(Box::new(Type::common(items.iter()))),
+            Value::Map(map) => Type::Map(Box::new(Type::Text), Box::new(Type::common(map.values()))),
+            Value::Function(_) => Type::Function,
+        }
+    }
+
+    fn common<'a>(mut values: impl Iterator<Item = &'a Value>) -> Type {
+        let Some(first) = values.next() else {
+            return Type::Any;
+        };
+        let ty = Type::of(first);
+        if values.all(|v| Type::of(v) == ty) { ty } else { Type::Any }
+    }
+
+    /// Whether `value` can be stored in a binding of this type.
+    /// Integers are accepted where a float or a big integer is expected
+    /// (see `coerce`).
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Type::Any, _) => true,
+            (Type::Int, Value::Integer(_)) => true,
+            (Type::BigInt, Value::BigInt(_) | Value::Integer(_)) => true,
+            (Type::Float, Value::Float(_) | Value::Integer(_)) => true,
+            (Type::Text, Value::String(_)) => true,
+            (Type::Bool, Value::Boolean(_)) => true,
+            (Type::Void, Value::Void) => true,
+            (Type::Function, Value::Function(_)) => true,
+            (Type::List(elem), Value::List(items)) => items.iter().all(|v| elem.accepts(v)),
+            (Type::Map(key, val), Value::Map(map)) => map
+                .iter()
+                .all(|(k, v)| key.accepts(&Value::String(k.clone())) && val.accepts(v)),
+            (Type::Option(_), Value::Void) => true,
+            (Type::Option(inner), v) => inner.accepts(v),
+            _ => false,
+        }
+    }
+
+    /// Converts an accepted value into the representation of this type.
+    pub fn coerce(&self, value: Value) -> Value {
+        match (self, value) {
+            (Type::Float, Value::Integer(n)) => Value::Float(n as f64),
+            (Type::BigInt, Value::Integer(n)) => Value::BigInt(BigInt::from_i64(n)),
+            (Type::List(elem), Value::List(items)) => {
+                Value::List(items.into_iter().map(|v| elem.coerce(v)).collect())
+            }
+            (Type::Map(_, val), Value::Map(map)) => {
+                Value::Map(map.into_iter().map(|(k, v)| (k, val.coerce(v))).collect())
+            }
+            (Type::Option(inner), value) if !matches!(value, Value::Void) => inner.coerce(value),
+            (_, value) => value,
+        }
+    }
+
+    fn arity(name: &str) -> usize {
+        match name {
+            "List" | "Option" => 1,
+            "Map" => 2,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "Int"),
+            Type::BigInt => write!(f, "BigInt"),
+            Type::Float => write!(f, "Float"),
+            Type::Text => write!(f, "Text"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Void => write!(f, "Void"),
+            Type::List(elem) => write!(f, "List<{}>", elem),
+            Type::Map(key, val) => write!(f, "Map<{}, {}>", key, val),
+            Type::Option(inner) => write!(f, "Option<{}>", inner),
+            Type::Function => write!(f, "Fn"),
+            Type::Any => write!(f, "Any"),
+        }
+    }
+}
+
+/// Resolves type names, including user aliases declared with `type Name = ...`.
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+    aliases: HashMap<String, Type>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn resolve(&self, expr: &TypeExpr) -> Result<Type, String> {
+        if expr.args.is_empty() {
+            if let Some(ty) = Type::builtin(&expr.name) {
+                return Ok(ty);
+            }
+            return self
+                .aliases
+                .get(&expr.name)
+                .cloned()
+                .ok_or_else(|| format!("Tipo desconocido '{}'", expr.name));
+        }
+
+        let expected = Type::arity(&expr.name);
+        if expected != expr.args.len() {
+            return Err(format!(
+                "'{}' espera {} argumento(s) de tipo pero recibió {}",
+                expr.name, expected, expr.args.len()
+            ));
+        }
+        let args = expr
+            .args
+            .iter()
+            .map(|a| self.resolve(a))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut args = args.into_iter().map(Box::new);
+        match expr.name.as_str() {
+            "List" => Ok(Type::List(args.next().unwrap())),
+            "Option" => Ok(Type::Option(args.next().unwrap())),
+            _ => {
+                let key = args.next().unwrap();
+                if !matches!(*key, Type::Text | Type::Any) {
+                    return Err(format!("Las claves de un Map deben ser Text, no '{}'", key));
+                }
+                Ok(Type::Map(key, args.next().unwrap()))
+            }
+        }
+    }
+
+    pub fn define_alias(&mut self, name: &str, target: &TypeExpr) -> Result<Type, String> {
+        if Type::builtin(name).is_some() {
+            return Err(format!("No se puede redefinir el tipo integrado '{}'", name));
+        }
+        let ty = self.resolve(target)?;
+        self.aliases.insert(name.to_string(), ty.clone());
+        Ok(ty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_spelling_resolves_to_its_canonical_type() {
+        let registry = TypeRegistry::new();
+        let cases = [
+            (&["int", "Int", "i32", "i64"][..], Type::Int),
+            (&["BigInt", "bigint"], Type::BigInt),
+            (&["float", "Float", "f32", "f64"], Type::Float),
+            (&["Text", "string", "String", "str"], Type::Text),
+            (&["bool", "Bool", "boolean"], Type::Bool),
+            (&["void", "Void"], Type::Void),
+            (&["Fn", "Function"], Type::Function),
+            (&["any", "Any"], Type::Any),
+        ];
+        for (spellings, canonical) in cases {
+            for spelling in spellings {
+                assert_eq!(registry.resolve(&TypeExpr::simple(spelling)), Ok(canonical.clone()), "{}", spelling);
+            }
+        }
+        assert_eq!(registry.resolve(&TypeExpr::simple("text")), Err("Tipo desconocido 'text'".to_string()));
+    }
+
+    #[test]
+    fn aliases_resolve_to_their_target() {
+        let mut registry = TypeRegistry::new();
+        assert_eq!(registry.define_alias("Name", &TypeExpr::simple("string")), Ok(Type::Text));
+        // Un alias puede apuntar a otro alias.
+        assert_eq!(registry.define_alias("Label", &TypeExpr::simple("Name")), Ok(Type::Text));
+        assert_eq!(registry.resolve(&TypeExpr::simple("Label")), Ok(Type::Text));
+        assert!(registry.resolve(&TypeExpr::simple("Label")).unwrap().accepts(&Value::String("x".to_string())));
+
+        assert_eq!(registry.define_alias("Name", &TypeExpr::simple("i32")), Ok(Type::Int));
+        assert_eq!(registry.resolve(&TypeExpr::simple("Name")), Ok(Type::Int));
+        // `Label` se resolvió al declararse y no sigue al nuevo `Name`.
+        assert_eq!(registry.resolve(&TypeExpr::simple("Label")), Ok(Type::Text));
+    }
+
+    #[test]
+    fn aliases_cannot_shadow_builtins_or_name_unknown_types() {
+        let mut registry = TypeRegistry::new();
+        for builtin in ["int", "Text", "string", "List", "Any"] {
+            assert_eq!(
+                registry.define_alias(builtin, &TypeExpr::simple("bool")),
+                Err(format!("No se puede redefinir el tipo integrado '{}'", builtin))
+            );
+        }
+        assert_eq!(registry.define_alias("Id", &TypeExpr::simple("Missing")), Err("Tipo desconocido 'Missing'".to_string()));
+        assert!(registry.resolve(&TypeExpr::simple("Id")).is_err());
+    }
+
+    fn generic(name: &str, args: Vec<TypeExpr>) -> TypeExpr {
+        TypeExpr { name: name.to_string(), args }
+    }
+
+    #[test]
+    fn generic_annotations_resolve_their_arguments() {
+        let mut registry = TypeRegistry::new();
+        registry.define_alias("Score", &TypeExpr::simple("f64")).unwrap();
+        let nested = generic("Map", vec![TypeExpr::simple("string"), generic("List", vec![TypeExpr::simple("Score")])]);
+        let ty = registry.resolve(&nested).unwrap();
+        assert_eq!(ty, Type::Map(Box::new(Type::Text), Box::new(Type::List(Box::new(Type::Float)))));
+        assert_eq!(ty.to_string(), "Map<Text, List<Float>>");
+        assert_eq!(
+            registry.resolve(&generic("Option", vec![TypeExpr::simple("i32")])),
+            Ok(Type::Option(Box::new(Type::Int)))
+        );
+        assert_eq!(registry.define_alias("Row", &generic("List", vec![TypeExpr::simple("int")])).unwrap().to_string(), "List<Int>");
+        assert_eq!(registry.resolve(&TypeExpr::simple("List")), Ok(Type::List(Box::new(Type::Any))));
+    }
+
+    #[test]
+    fn malformed_generics_are_rejected() {
+        let mut registry = TypeRegistry::new();
+        registry.define_alias("Row", &generic("List", vec![TypeExpr::simple("int")])).unwrap();
+        let int = || TypeExpr::simple("int");
+        let cases = [
+            (generic("List", vec![int(), int()]), "'List' espera 1 argumento(s) de tipo pero recibió 2"),
+            (generic("Map", vec![TypeExpr::simple("Text")]), "'Map' espera 2 argumento(s) de tipo pero recibió 1"),
+            (generic("int", vec![TypeExpr::simple("Text")]), "'int' espera 0 argumento(s) de tipo pero recibió 1"),
+            (generic("Row", vec![int()]), "'Row' espera 0 argumento(s) de tipo pero recibió 1"),
+            (generic("Map", vec![int(), int()]), "Las claves de un Map deben ser Text, no 'Int'"),
+            (generic("List", vec![TypeExpr::simple("Missing")]), "Tipo desconocido 'Missing'"),
+        ];
+        for (expr, message) in cases {
+            assert_eq!(registry.resolve(&expr), Err(message.to_string()), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn element_types_are_checked_and_coerced() {
+        let int_list = |items: &[i64]| Value::List(items.iter().map(|&n| Value::Integer(n)).collect());
+        let floats = Type::List(Box::new(Type::Float));
+        assert!(floats.accepts(&int_list(&[1, 2])));
+        assert_eq!(floats.coerce(int_list(&[1, 2])), Value::List(vec![Value::Float(1.0), Value::Float(2.0)]));
+        assert!(!Type::List(Box::new(Type::Int)).accepts(&Value::List(vec![Value::Integer(1), Value::Float(1.5)])));
+
+        let grid = Type::List(Box::new(Type::List(Box::new(Type::Int))));
+        assert!(grid.accepts(&Value::List(vec![int_list(&[1]), int_list(&[])])));
+        assert!(!grid.accepts(&Value::List(vec![int_list(&[1]), Value::Integer(2)])));
+
+        let scores = Type::Map(Box::new(Type::Text), Box::new(Type::Option(Box::new(Type::Float))));
+        let map = Value::Map([("a".to_string(), Value::Integer(1)), ("b".to_string(), Value::Void)].into_iter().collect());
+        assert!(scores.accepts(&map));
+        let expected = Value::Map([("a".to_string(), Value::Float(1.0)), ("b".to_string(), Value::Void)].into_iter().collect());
+        assert_eq!(scores.coerce(map), expected);
+        assert!(!scores.accepts(&Value::Map([("a".to_string(), Value::Boolean(true))].into_iter().collect())));
+
+        assert_eq!(Type::of(&int_list(&[1, 2])), Type::List(Box::new(Type::Int)));
+        assert_eq!(Type::of(&Value::List(vec![Value::Integer(1), Value::Float(1.0)])).to_string(), "List<Any>");
+    }
+}